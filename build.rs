@@ -25,8 +25,14 @@ fn main() {
     let dest_path = Path::new(&out_dir).join("tags.rs");
     let mut dest = File::create(dest_path).unwrap();
     let src = BufReader::new(File::open(src_path).unwrap());
-    let tags = src.lines().map(|s| Tag::from_str(&s.unwrap()).unwrap());
-    for tag in tags  {
+    let mut tags = src.lines().map(|s| Tag::from_str(&s.unwrap()).unwrap()).collect::<Vec<_>>();
+    for &tag in &tags {
         writeln!(dest, "pub const {}: Tag = Tag {{ dword: {} }};", tag, tag.dword).unwrap();
     }
+    tags.sort();
+    writeln!(dest, "pub(crate) const TAGS: &[Tag] = &[").unwrap();
+    for tag in tags {
+        writeln!(dest, "    {tag},").unwrap();
+    }
+    writeln!(dest, "];").unwrap();
 }
@@ -0,0 +1,142 @@
+use crate::field::*;
+use crate::record::*;
+use crate::strings::*;
+use crate::view::*;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AiPackage {
+    Wander(AiWander),
+    Travel(AiTravel),
+    Escort { target: AiTarget, cell: Option<StringZ> },
+    Follow { target: AiTarget, cell: Option<StringZ> },
+    Activate(AiActivate),
+}
+
+impl AiPackage {
+    pub fn tag(&self) -> Tag {
+        match self {
+            AiPackage::Wander(_) => AI_W,
+            AiPackage::Travel(_) => AI_T,
+            AiPackage::Escort { .. } => AI_E,
+            AiPackage::Follow { .. } => AI_F,
+            AiPackage::Activate(_) => AI_A,
+        }
+    }
+
+    fn into_fields(self) -> Vec<(Tag, Field)> {
+        let (field, cell) = match self {
+            AiPackage::Wander(v) => ((AI_W, Field::AiWander(v)), None),
+            AiPackage::Travel(v) => ((AI_T, Field::AiTravel(v)), None),
+            AiPackage::Escort { target, cell } => ((AI_E, Field::AiTarget(target)), cell),
+            AiPackage::Follow { target, cell } => ((AI_F, Field::AiTarget(target)), cell),
+            AiPackage::Activate(v) => ((AI_A, Field::AiActivate(v)), None),
+        };
+        let mut fields = vec![field];
+        fields.extend(cell.map(|x| (CNDT, Field::StringZ(x))));
+        fields
+    }
+}
+
+fn is_ai_package_field(tag: Tag) -> bool { matches!(tag, AI_W | AI_T | AI_E | AI_F | AI_A) }
+
+fn check_actor(record: &Record) -> Result<(), RecordViewError> {
+    if record.tag != NPC_ && record.tag != CREA {
+        return Err(RecordViewError::TagsMismatch { expected: &[NPC_, CREA], actual: record.tag });
+    }
+    Ok(())
+}
+
+impl Record {
+    pub fn ai_packages(&self) -> Result<Vec<AiPackage>, RecordViewError> {
+        check_actor(self)?;
+        let mut packages = Vec::new();
+        for (field_index, (field_tag, field)) in self.fields.iter().enumerate() {
+            let unexpected = || RecordViewError::UnexpectedField { record_tag: self.tag, field_tag: *field_tag, field_index };
+            let package = match (*field_tag, field) {
+                (AI_W, Field::AiWander(v)) => AiPackage::Wander(v.clone()),
+                (AI_T, Field::AiTravel(v)) => AiPackage::Travel(v.clone()),
+                (AI_E, Field::AiTarget(v)) => AiPackage::Escort { target: v.clone(), cell: None },
+                (AI_F, Field::AiTarget(v)) => AiPackage::Follow { target: v.clone(), cell: None },
+                (AI_A, Field::AiActivate(v)) => AiPackage::Activate(v.clone()),
+                (CNDT, Field::StringZ(v)) => {
+                    let prev_tag = field_index.checked_sub(1).map(|x| self.fields[x].0);
+                    match packages.last_mut() {
+                        Some(AiPackage::Escort { cell, .. } | AiPackage::Follow { cell, .. })
+                            if cell.is_none() && matches!(prev_tag, Some(AI_E | AI_F)) => *cell = Some(v.clone()),
+                        _ => return Err(unexpected()),
+                    }
+                    continue;
+                },
+                (tag, _) if is_ai_package_field(tag) || tag == CNDT => return Err(unexpected()),
+                _ => continue
+            };
+            packages.push(package);
+        }
+        Ok(packages)
+    }
+
+    pub fn set_ai_packages(&mut self, packages: Vec<AiPackage>) -> Result<(), RecordViewError> {
+        check_actor(self)?;
+        let index = self.fields.iter().position(|x| is_ai_package_field(x.0) || x.0 == CNDT).unwrap_or_else(|| {
+            self.fields.iter().rposition(|x| matches!(x.0, AIDT | DODT | DNAM)).map_or(self.fields.len(), |x| x + 1)
+        });
+        let mut fields = std::mem::take(&mut self.fields);
+        let tail = fields.split_off(index);
+        fields.extend(packages.into_iter().flat_map(AiPackage::into_fields));
+        fields.extend(tail.into_iter().filter(|x| !is_ai_package_field(x.0) && x.0 != CNDT));
+        self.fields = fields;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn target(actor_id: &str) -> AiTarget {
+        AiTarget {
+            pos: Pos { x: 0.0, y: 0.0, z: 0.0 },
+            duration: 24,
+            actor_id: actor_id.into(),
+            reset: false,
+            flags: AiTargetFlags::empty()
+        }
+    }
+
+    #[test]
+    fn ai_packages_round_trip() {
+        let wander = AiWander { distance: 512, duration: 5, time_of_day: 0, idle: [60, 20, 10, 0, 0, 0, 0, 0], repeat: true };
+        let mut record = Record {
+            tag: NPC_,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (NAME, Field::StringZ("fargoth".into())),
+                (AI_W, Field::AiWander(wander.clone())),
+                (AI_E, Field::AiTarget(target("player"))),
+                (CNDT, Field::StringZ("Seyda Neen".into())),
+                (AI_F, Field::AiTarget(target("hrisskar"))),
+                (XSCL, Field::F32(1.0)),
+            ]
+        };
+        let packages = record.ai_packages().unwrap();
+        assert_eq!(packages, vec![
+            AiPackage::Wander(wander.clone()),
+            AiPackage::Escort { target: target("player"), cell: Some("Seyda Neen".into()) },
+            AiPackage::Follow { target: target("hrisskar"), cell: None },
+        ]);
+        let original = record.clone();
+        record.set_ai_packages(packages.clone()).unwrap();
+        assert_eq!(record, original);
+        record.set_ai_packages(vec![AiPackage::Follow { target: target("player"), cell: None }]).unwrap();
+        assert_eq!(record.fields.iter().map(|x| x.0).collect::<Vec<_>>(), vec![NAME, AI_F, XSCL]);
+        record.fields.insert(1, (CNDT, Field::StringZ("Balmora".into())));
+        assert_eq!(
+            record.ai_packages(),
+            Err(RecordViewError::UnexpectedField { record_tag: NPC_, field_tag: CNDT, field_index: 1 })
+        );
+        let cell = Record { tag: CELL, flags: RecordFlags::empty(), fields: Vec::new() };
+        let error = cell.ai_packages().unwrap_err();
+        assert_eq!(error, RecordViewError::TagsMismatch { expected: &[NPC_, CREA], actual: CELL });
+        assert_eq!(error.to_string(), "NPC_ or CREA record expected, but CELL record found");
+    }
+}
@@ -0,0 +1,366 @@
+use crate::field::*;
+use crate::record::*;
+use crate::strings::*;
+use crate::view::*;
+use std::collections::HashMap;
+
+pub const CELL_SIZE: i32 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellBounds {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl CellBounds {
+    pub fn contains(&self, pos: &Pos) -> bool {
+        pos.x >= self.min_x && pos.x < self.max_x && pos.y >= self.min_y && pos.y < self.max_y
+    }
+}
+
+impl Grid {
+    pub fn from_pos(pos: &Pos) -> Grid {
+        Grid {
+            x: (pos.x / CELL_SIZE as f32).floor() as i32,
+            y: (pos.y / CELL_SIZE as f32).floor() as i32,
+        }
+    }
+
+    pub fn bounds(&self) -> CellBounds {
+        CellBounds {
+            min_x: (self.x * CELL_SIZE) as f32,
+            min_y: (self.y * CELL_SIZE) as f32,
+            max_x: ((self.x + 1) * CELL_SIZE) as f32,
+            max_y: ((self.y + 1) * CELL_SIZE) as f32,
+        }
+    }
+
+    pub fn center(&self) -> Pos {
+        let half = CELL_SIZE as f32 / 2.0;
+        Pos { x: (self.x * CELL_SIZE) as f32 + half, y: (self.y * CELL_SIZE) as f32 + half, z: 0.0 }
+    }
+
+    pub fn contains(&self, pos: &Pos) -> bool { Grid::from_pos(pos) == *self }
+}
+
+impl CellPosition {
+    pub fn grid(&self) -> Option<Grid> {
+        match *self {
+            CellPosition::Exterior { x, y } => Some(Grid { x, y }),
+            CellPosition::Interior { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct CellReference {
+    pub fields: Vec<(Tag, Field)>,
+}
+
+macro_rules! cell_reference_field {
+    ($field:ident, $field_mut:ident: $field_tag:ident => $variant:ident($ty:ty)) => {
+        pub fn $field(&self) -> Option<&$ty> {
+            self.fields.iter().find_map(|(tag, field)| match field {
+                Field::$variant(v) if *tag == $field_tag => Some(v),
+                _ => None
+            })
+        }
+
+        pub fn $field_mut(&mut self) -> Option<&mut $ty> {
+            self.fields.iter_mut().find_map(|(tag, field)| match field {
+                Field::$variant(v) if *tag == $field_tag => Some(v),
+                _ => None
+            })
+        }
+    };
+}
+
+impl CellReference {
+    cell_reference_field!(ref_num, ref_num_mut: FRMR => I32(i32));
+    cell_reference_field!(moved_ref_num, moved_ref_num_mut: MVRF => I32(i32));
+    cell_reference_field!(moved_to, moved_to_mut: CNDT => Grid(Grid));
+    cell_reference_field!(id, id_mut: NAME => StringZ(StringZ));
+    cell_reference_field!(scale, scale_mut: XSCL => F32(f32));
+    cell_reference_field!(pos_rot, pos_rot_mut: DATA => PosRot(PosRot));
+    cell_reference_field!(door_pos_rot, door_pos_rot_mut: DODT => PosRot(PosRot));
+    cell_reference_field!(door_cell, door_cell_mut: DNAM => StringZ(StringZ));
+
+    pub fn is_moved(&self) -> bool { self.fields.iter().any(|x| x.0 == MVRF) }
+
+    pub fn is_deleted(&self) -> bool { self.fields.iter().any(|x| x.0 == DELE) }
+
+    pub fn grid(&self) -> Option<Grid> { self.pos_rot().map(|x| Grid::from_pos(&x.pos)) }
+
+    pub fn lies_in(&self, position: &CellPosition) -> Option<bool> {
+        let expected = self.moved_to().cloned().or_else(|| position.grid())?;
+        Some(expected.contains(&self.pos_rot()?.pos))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CellReferences {
+    pub header: Record,
+    pub references: Vec<CellReference>,
+}
+
+impl CellReferences {
+    pub fn header(&self) -> Result<CellRecord, RecordViewError> { CellRecord::try_from(&self.header) }
+
+    pub fn position(&self) -> Option<&CellPosition> {
+        self.header.fields.iter().find_map(|x| if let (DATA, Field::Cell(v)) = x { Some(&v.position) } else { None })
+    }
+
+    pub fn misplaced(&self) -> impl Iterator<Item=(usize, Grid)> + '_ {
+        let position = self.position();
+        self.references.iter().enumerate().filter_map(move |(index, reference)| {
+            if reference.lies_in(position?)? { None } else { Some((index, reference.grid()?)) }
+        })
+    }
+
+    pub fn find(&self, ref_num: i32) -> Option<&CellReference> {
+        self.references.iter().find(|x| x.ref_num() == Some(&ref_num))
+    }
+
+    pub fn find_mut(&mut self, ref_num: i32) -> Option<&mut CellReference> {
+        self.references.iter_mut().find(|x| x.ref_num() == Some(&ref_num))
+    }
+}
+
+impl TryFrom<Record> for CellReferences {
+    type Error = (RecordViewError, Record);
+
+    fn try_from(record: Record) -> Result<Self, Self::Error> {
+        if record.tag != CELL {
+            return Err((RecordViewError::TagMismatch { expected: CELL, actual: record.tag }, record));
+        }
+        let mut header = Vec::new();
+        let mut references: Vec<CellReference> = Vec::new();
+        for (tag, field) in record.fields {
+            let starts_reference = match tag {
+                MVRF => true,
+                FRMR => references.last().is_none_or(|x| !x.is_moved() || x.ref_num().is_some()),
+                _ => false
+            };
+            if starts_reference {
+                references.push(CellReference::default());
+            }
+            match references.last_mut() {
+                Some(reference) => reference.fields.push((tag, field)),
+                None => header.push((tag, field)),
+            }
+        }
+        Ok(CellReferences { header: Record { tag: CELL, flags: record.flags, fields: header }, references })
+    }
+}
+
+impl TryFrom<&Record> for CellReferences {
+    type Error = RecordViewError;
+
+    fn try_from(record: &Record) -> Result<Self, Self::Error> {
+        if record.tag != CELL {
+            return Err(RecordViewError::TagMismatch { expected: CELL, actual: record.tag });
+        }
+        Self::try_from(record.clone()).map_err(|x| x.0)
+    }
+}
+
+impl From<CellReferences> for Record {
+    fn from(cell: CellReferences) -> Record {
+        let mut record = cell.header;
+        record.fields.extend(cell.references.into_iter().flat_map(|x| x.fields));
+        record
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Relocation {
+    pub ref_num: i32,
+    pub from: Grid,
+    pub to: Grid,
+    pub moved_ref: bool,
+}
+
+pub fn is_master_ref_num(ref_num: i32) -> bool { (ref_num as u32) >> 24 != 0 }
+
+fn adjust_references_count(header: &mut Record, delta: i32) {
+    for (tag, field) in &mut header.fields {
+        if let (NAM0, Field::I32(count)) = (*tag, field) {
+            *count += delta;
+        }
+    }
+}
+
+pub fn relocate_references(records: Vec<Record>) -> (Vec<Record>, Vec<Relocation>) {
+    let mut cells = records.into_iter()
+        .map(|record| CellReferences::try_from(record).map_err(|x| x.1))
+        .collect::<Vec<_>>();
+    let mut exteriors = HashMap::new();
+    for (index, cell) in cells.iter().enumerate() {
+        if let Some(grid) = cell.as_ref().ok().and_then(|x| x.position()).and_then(|x| x.grid()) {
+            exteriors.entry(grid).or_insert(index);
+        }
+    }
+    let mut relocations = Vec::new();
+    let mut moved = Vec::new();
+    for cell in cells.iter_mut().filter_map(|x| x.as_mut().ok()) {
+        let Some(from) = cell.position().and_then(|x| x.grid()) else { continue; };
+        let position = CellPosition::Exterior { x: from.x, y: from.y };
+        let mut kept = Vec::with_capacity(cell.references.len());
+        for mut reference in std::mem::take(&mut cell.references) {
+            let misplaced = !reference.is_deleted() && reference.lies_in(&position) == Some(false);
+            let (true, Some(&ref_num), Some(to)) = (misplaced, reference.ref_num(), reference.grid()) else {
+                kept.push(reference);
+                continue;
+            };
+            if is_master_ref_num(ref_num) {
+                reference.fields.retain(|x| x.0 != MVRF && x.0 != CNDT);
+                if to != from {
+                    reference.fields.splice(0 .. 0, [(MVRF, Field::I32(ref_num)), (CNDT, Field::Grid(to.clone()))]);
+                    relocations.push(Relocation { ref_num, from: from.clone(), to, moved_ref: true });
+                }
+                kept.push(reference);
+            } else {
+                reference.fields.retain(|x| x.0 != MVRF && x.0 != CNDT);
+                adjust_references_count(&mut cell.header, -1);
+                relocations.push(Relocation { ref_num, from: from.clone(), to: to.clone(), moved_ref: false });
+                moved.push((to, reference));
+            }
+        }
+        cell.references = kept;
+    }
+    for (to, reference) in moved {
+        let index = *exteriors.entry(to.clone()).or_insert_with(|| {
+            cells.push(Ok(CellReferences {
+                header: Record {
+                    tag: CELL,
+                    flags: RecordFlags::empty(),
+                    fields: vec![
+                        (NAME, Field::StringZ(StringZ::default())),
+                        (DATA, Field::Cell(Cell { flags: CellFlags::empty(), position: CellPosition::Exterior { x: to.x, y: to.y } })),
+                        (NAM0, Field::I32(0)),
+                    ]
+                },
+                references: Vec::new()
+            }));
+            cells.len() - 1
+        });
+        let Ok(cell) = &mut cells[index] else { unreachable!() };
+        adjust_references_count(&mut cell.header, 1);
+        cell.references.push(reference);
+    }
+    let records = cells.into_iter().map(|x| x.map_or_else(|record| record, Record::from)).collect();
+    (records, relocations)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn pos_rot(x: f32) -> Field {
+        Field::PosRot(PosRot { pos: Pos { x, y: 0.0, z: 0.0 }, rot: Rot { x: 0.0, y: 0.0, z: 0.0 } })
+    }
+
+    #[test]
+    fn cell_references_round_trip() {
+        let record = Record {
+            tag: CELL,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (NAME, Field::StringZ("Seyda Neen".into())),
+                (DATA, Field::Cell(Cell { flags: CellFlags::empty(), position: CellPosition::Exterior { x: -2, y: -9 } })),
+                (NAM0, Field::I32(2)),
+                (FRMR, Field::I32(1)),
+                (NAME, Field::StringZ("chargen boat".into())),
+                (XSCL, Field::F32(1.5)),
+                (DATA, pos_rot(1.0)),
+                (MVRF, Field::I32(2)),
+                (CNDT, Field::Grid(Grid { x: -3, y: -9 })),
+                (FRMR, Field::I32(2)),
+                (NAME, Field::StringZ("fargoth".into())),
+                (DATA, pos_rot(2.0)),
+                (FRMR, Field::I32(3)),
+                (NAME, Field::StringZ("flora_kelp_01".into())),
+                (DELE, Field::I32(0)),
+                (DATA, pos_rot(3.0)),
+            ]
+        };
+        let cell = CellReferences::try_from(&record).unwrap();
+        assert_eq!(cell.header.fields.len(), 3);
+        assert_eq!(cell.header().unwrap().id().unwrap().string, "Seyda Neen");
+        assert_eq!(cell.references.len(), 3);
+        assert_eq!(cell.references[0].scale(), Some(&1.5));
+        assert_eq!(cell.references[1].moved_to(), Some(&Grid { x: -3, y: -9 }));
+        assert_eq!(cell.find(2).unwrap().id().unwrap().string, "fargoth");
+        assert!(cell.references[2].is_deleted());
+        assert_eq!(Record::from(cell), record);
+    }
+
+    #[test]
+    fn misplaced_references() {
+        assert_eq!(Grid::from_pos(&Pos { x: -1.0, y: 8192.0, z: 0.0 }), Grid { x: -1, y: 1 });
+        assert!(Grid { x: -2, y: -9 }.bounds().contains(&Pos { x: -16384.0, y: -70000.0, z: 0.0 }));
+        let cell = CellReferences {
+            header: Record {
+                tag: CELL,
+                flags: RecordFlags::empty(),
+                fields: vec![
+                    (DATA, Field::Cell(Cell { flags: CellFlags::empty(), position: CellPosition::Exterior { x: 0, y: 0 } })),
+                ]
+            },
+            references: vec![
+                CellReference { fields: vec![(FRMR, Field::I32(1)), (DATA, pos_rot(100.0))] },
+                CellReference { fields: vec![(FRMR, Field::I32(2)), (DATA, pos_rot(9000.0))] },
+                CellReference { fields: vec![(FRMR, Field::I32(3))] },
+            ]
+        };
+        assert_eq!(cell.misplaced().collect::<Vec<_>>(), vec![(1, Grid { x: 1, y: 0 })]);
+    }
+
+    #[test]
+    fn relocate_exterior_references() {
+        let exterior = |x: i32, references: Vec<(i32, f32)>| {
+            let mut fields = vec![
+                (NAME, Field::StringZ("".into())),
+                (DATA, Field::Cell(Cell { flags: CellFlags::empty(), position: CellPosition::Exterior { x, y: 0 } })),
+                (NAM0, Field::I32(references.len() as i32)),
+            ];
+            for (ref_num, pos) in references {
+                fields.extend([(FRMR, Field::I32(ref_num)), (DATA, pos_rot(pos))]);
+            }
+            Record { tag: CELL, flags: RecordFlags::empty(), fields }
+        };
+        let master_ref = 1 << 24 | 7;
+        let records = vec![exterior(0, vec![(1, 100.0), (2, 9000.0), (master_ref, 9000.0)]), exterior(1, vec![])];
+        let (records, relocations) = relocate_references(records);
+        assert_eq!(relocations, vec![
+            Relocation { ref_num: 2, from: Grid { x: 0, y: 0 }, to: Grid { x: 1, y: 0 }, moved_ref: false },
+            Relocation { ref_num: master_ref, from: Grid { x: 0, y: 0 }, to: Grid { x: 1, y: 0 }, moved_ref: true },
+        ]);
+        let source = CellReferences::try_from(&records[0]).unwrap();
+        assert_eq!(source.header.fields[2].1, Field::I32(2));
+        assert_eq!(source.references[1].moved_to(), Some(&Grid { x: 1, y: 0 }));
+        let target = CellReferences::try_from(&records[1]).unwrap();
+        assert_eq!(target.header.fields[2].1, Field::I32(1));
+        assert_eq!(target.references[0].ref_num(), Some(&2));
+        assert_eq!(relocate_references(records.clone()), (records, Vec::new()));
+        let mut moved_back = exterior(0, vec![(2, 9000.0)]);
+        moved_back.fields.extend([
+            (MVRF, Field::I32(master_ref)),
+            (CNDT, Field::Grid(Grid { x: 1, y: 0 })),
+            (FRMR, Field::I32(master_ref)),
+            (DATA, pos_rot(100.0)),
+        ]);
+        let (records, relocations) = relocate_references(vec![moved_back]);
+        assert_eq!(relocations, vec![
+            Relocation { ref_num: 2, from: Grid { x: 0, y: 0 }, to: Grid { x: 1, y: 0 }, moved_ref: false },
+        ]);
+        let source = CellReferences::try_from(&records[0]).unwrap();
+        assert_eq!(source.header.fields[2].1, Field::I32(0));
+        assert_eq!(source.references[0].fields, vec![(FRMR, Field::I32(master_ref)), (DATA, pos_rot(100.0))]);
+        let created = CellReferences::try_from(&records[1]).unwrap();
+        assert_eq!(created.position(), Some(&CellPosition::Exterior { x: 1, y: 0 }));
+        assert_eq!(created.header.fields[2], (NAM0, Field::I32(1)));
+    }
+}
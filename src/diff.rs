@@ -0,0 +1,263 @@
+use crate::field::*;
+use crate::record::*;
+use std::collections::HashMap;
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FieldChange {
+    Inserted { index: usize, tag: Tag, field: Field },
+    Removed { index: usize, tag: Tag, field: Field },
+    Changed { old_index: usize, new_index: usize, tag: Tag, old: Field, new: Field },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct RecordDiff {
+    pub flags: Option<(RecordFlags, RecordFlags)>,
+    pub fields: Vec<FieldChange>,
+}
+
+impl RecordDiff {
+    pub fn is_empty(&self) -> bool { self.flags.is_none() && self.fields.is_empty() }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RecordChange {
+    Added { index: usize, record: Record },
+    Removed { index: usize, record: Record },
+    Modified { old_index: usize, new_index: usize, tag: Tag, id: Option<RecordId>, diff: RecordDiff },
+}
+
+pub(crate) enum Op {
+    Keep,
+    Remove,
+    Insert,
+}
+
+fn lcs_lengths(old: &[(Tag, Field)], new: &[(Tag, Field)], reverse: bool) -> Vec<u32> {
+    let (n, m) = (old.len(), new.len());
+    let mut row = vec![0u32; m + 1];
+    for i in 0 .. n {
+        let a = &old[if reverse { n - 1 - i } else { i }];
+        let mut diagonal = 0;
+        for j in 0 .. m {
+            let b = &new[if reverse { m - 1 - j } else { j }];
+            let above = row[j + 1];
+            row[j + 1] = if a == b { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+fn lcs_ops_into(old: &[(Tag, Field)], new: &[(Tag, Field)], ops: &mut Vec<Op>) {
+    if old.is_empty() || new.is_empty() {
+        ops.extend(old.iter().map(|_| Op::Remove));
+        ops.extend(new.iter().map(|_| Op::Insert));
+        return;
+    }
+    if old.len() == 1 {
+        if let Some(j) = new.iter().position(|x| *x == old[0]) {
+            ops.extend((0 .. j).map(|_| Op::Insert));
+            ops.push(Op::Keep);
+            ops.extend((j + 1 .. new.len()).map(|_| Op::Insert));
+        } else {
+            ops.push(Op::Remove);
+            ops.extend(new.iter().map(|_| Op::Insert));
+        }
+        return;
+    }
+    let mid = old.len() / 2;
+    let forward = lcs_lengths(&old[.. mid], new, false);
+    let backward = lcs_lengths(&old[mid ..], new, true);
+    let mut split = 0;
+    for j in 1 ..= new.len() {
+        if forward[j] + backward[new.len() - j] > forward[split] + backward[new.len() - split] {
+            split = j;
+        }
+    }
+    lcs_ops_into(&old[.. mid], &new[.. split], ops);
+    lcs_ops_into(&old[mid ..], &new[split ..], ops);
+}
+
+pub(crate) fn lcs_ops(old: &[(Tag, Field)], new: &[(Tag, Field)]) -> Vec<Op> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    lcs_ops_into(old, new, &mut ops);
+    ops
+}
+
+fn flush_run(
+    old: &[(Tag, Field)], new: &[(Tag, Field)],
+    removed: &mut Vec<usize>, inserted: &mut Vec<usize>, changes: &mut Vec<FieldChange>
+) {
+    let mut inserted_left = inserted.iter().copied().map(Some).collect::<Vec<_>>();
+    for &old_index in removed.iter() {
+        let tag = old[old_index].0;
+        let pair = inserted_left.iter_mut().find(|x| x.is_some_and(|new_index| new[new_index].0 == tag));
+        if let Some(new_index) = pair.and_then(|x| x.take()) {
+            changes.push(FieldChange::Changed {
+                old_index, new_index, tag, old: old[old_index].1.clone(), new: new[new_index].1.clone()
+            });
+        } else {
+            changes.push(FieldChange::Removed { index: old_index, tag, field: old[old_index].1.clone() });
+        }
+    }
+    for new_index in inserted_left.into_iter().flatten() {
+        let (tag, field) = &new[new_index];
+        changes.push(FieldChange::Inserted { index: new_index, tag: *tag, field: field.clone() });
+    }
+    removed.clear();
+    inserted.clear();
+}
+
+pub fn diff_records(old: &Record, new: &Record) -> RecordDiff {
+    let flags = if old.flags != new.flags { Some((old.flags, new.flags)) } else { None };
+    let prefix = old.fields.iter().zip(new.fields.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = old.fields[prefix ..].iter().rev().zip(new.fields[prefix ..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let old_fields = &old.fields[.. old.fields.len() - suffix];
+    let new_fields = &new.fields[.. new.fields.len() - suffix];
+    let mut fields = Vec::new();
+    let mut removed = Vec::new();
+    let mut inserted = Vec::new();
+    let (mut i, mut j) = (prefix, prefix);
+    for op in lcs_ops(&old_fields[prefix ..], &new_fields[prefix ..]) {
+        match op {
+            Op::Keep => {
+                flush_run(old_fields, new_fields, &mut removed, &mut inserted, &mut fields);
+                i += 1;
+                j += 1;
+            },
+            Op::Remove => {
+                removed.push(i);
+                i += 1;
+            },
+            Op::Insert => {
+                inserted.push(j);
+                j += 1;
+            },
+        }
+    }
+    flush_run(old_fields, new_fields, &mut removed, &mut inserted, &mut fields);
+    RecordDiff { flags, fields }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub(crate) enum RecordKey {
+    Id(RecordId, usize),
+    Nth(usize),
+}
+
+impl RecordKey {
+    pub(crate) fn id(&self) -> Option<RecordId> {
+        match self { RecordKey::Id(id, _) => Some(id.clone()), RecordKey::Nth(_) => None }
+    }
+}
+
+pub(crate) fn record_keys(records: &[Record]) -> Vec<(Tag, RecordKey)> {
+    let mut nth = HashMap::new();
+    let mut occurrences = HashMap::new();
+    records.iter().map(|record| {
+        let key = if let Some(id) = record.id() {
+            let occurrence = occurrences.entry((record.tag, id.clone())).or_insert(0);
+            *occurrence += 1;
+            RecordKey::Id(id, *occurrence - 1)
+        } else {
+            let n = nth.entry(record.tag).or_insert(0);
+            *n += 1;
+            RecordKey::Nth(*n - 1)
+        };
+        (record.tag, key)
+    }).collect()
+}
+
+pub fn diff_files(old: &[Record], new: &[Record]) -> Vec<RecordChange> {
+    let mut old_index = record_keys(old).into_iter().enumerate().map(|(i, key)| (key, i)).collect::<HashMap<_, _>>();
+    let mut changes = Vec::new();
+    for (new_index, (tag, key)) in record_keys(new).into_iter().enumerate() {
+        if let Some(old_index) = old_index.remove(&(tag, key.clone())) {
+            let diff = diff_records(&old[old_index], &new[new_index]);
+            if !diff.is_empty() {
+                let id = key.id();
+                changes.push(RecordChange::Modified { old_index, new_index, tag, id, diff });
+            }
+        } else {
+            changes.push(RecordChange::Added { index: new_index, record: new[new_index].clone() });
+        }
+    }
+    let mut removed = old_index.into_values().collect::<Vec<_>>();
+    removed.sort_unstable();
+    changes.extend(removed.into_iter().map(|index| RecordChange::Removed { index, record: old[index].clone() }));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::diff::*;
+    use std::str::FromStr;
+
+    fn misc(id: &str, value: i32) -> Record {
+        Record {
+            tag: Tag::from_str("MISC").unwrap(),
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (NAME, Field::StringZ(id.into())),
+                (MODL, Field::StringZ("m.nif".into())),
+                (FNAM, Field::StringZ(id.to_uppercase().into())),
+                (Tag::from_str("MCDT").unwrap(), Field::I32(value)),
+            ]
+        }
+    }
+
+    #[test]
+    fn record_field_changes() {
+        let old = misc("a", 1);
+        let mut new = misc("a", 2);
+        new.fields.remove(1);
+        new.fields.push((SCRI, Field::StringZ("script".into())));
+        new.flags = RecordFlags::PERSIST;
+        let diff = diff_records(&old, &new);
+        assert_eq!(diff.flags, Some((RecordFlags::empty(), RecordFlags::PERSIST)));
+        assert_eq!(diff.fields, vec![
+            FieldChange::Removed { index: 1, tag: MODL, field: Field::StringZ("m.nif".into()) },
+            FieldChange::Changed {
+                old_index: 3, new_index: 2, tag: Tag::from_str("MCDT").unwrap(), old: Field::I32(1), new: Field::I32(2)
+            },
+            FieldChange::Inserted { index: 3, tag: SCRI, field: Field::StringZ("script".into()) },
+        ]);
+        assert!(diff_records(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn file_changes() {
+        let old = vec![misc("a", 1), misc("b", 1)];
+        let new = vec![misc("b", 1), misc("c", 1), misc("A", 1)];
+        let changes = diff_files(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[0], RecordChange::Added { index: 1, .. }));
+        assert!(matches!(&changes[1], RecordChange::Modified { old_index: 0, new_index: 2, .. }));
+        let old = vec![misc("a", 1), misc("a", 2)];
+        let new = vec![misc("a", 1), misc("a", 3)];
+        let changes = diff_files(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], RecordChange::Modified { old_index: 1, new_index: 1, .. }));
+        let changes = diff_files(&old, &new[.. 1]);
+        assert!(matches!(&changes[..], [RecordChange::Removed { index: 1, .. }]));
+    }
+
+    #[test]
+    fn long_field_lists() {
+        let fields = |values: &mut dyn Iterator<Item=i32>| Record {
+            tag: Tag::from_str("MISC").unwrap(),
+            flags: RecordFlags::empty(),
+            fields: values.map(|x| (INDX, Field::I32(x))).collect()
+        };
+        let old = fields(&mut (0 .. 2000));
+        let new = fields(&mut (0 .. 2000).filter(|x| x % 7 != 3).chain(2000 .. 2010));
+        let diff = diff_records(&old, &new);
+        let removed = diff.fields.iter().filter(|x| matches!(x, FieldChange::Removed { .. })).count();
+        let changed = diff.fields.iter().filter(|x| matches!(x, FieldChange::Changed { .. })).count();
+        let inserted = diff.fields.iter().filter(|x| matches!(x, FieldChange::Inserted { .. })).count();
+        assert_eq!(removed + changed, (0 .. 2000).filter(|x| x % 7 == 3).count());
+        assert_eq!(changed + inserted, 10);
+    }
+}
@@ -6,6 +6,8 @@ use educe::Educe;
 use either::{Either, Left, Right};
 use enum_derive_2018::{EnumDisplay, EnumFromStr};
 use enumn::N;
+use flate2::Compression;
+use flate2::write::{ZlibDecoder, ZlibEncoder};
 use macro_attr_2018::macro_attr;
 use nameof::name_of;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
@@ -14,8 +16,10 @@ use serde::de::{self, DeserializeSeed, Unexpected, VariantAccess};
 use serde::ser::SerializeStruct;
 use serde::ser::Error as ser_Error;
 use serde_serialize_seed::{SerializeSeed, ValueWithSeed};
+use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, Write};
 use std::mem::transmute;
 use std::ops::{Index, IndexMut};
 use std::str::FromStr;
@@ -36,6 +40,36 @@ impl Newline {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ZipMode {
+    Compressed,
+    Uncompressed
+}
+
+impl ZipMode {
+    pub fn encode(self, bytes: &[u8]) -> Cow<'_, [u8]> {
+        match self {
+            ZipMode::Uncompressed => Cow::Borrowed(bytes),
+            ZipMode::Compressed => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(5));
+                encoder.write_all(bytes).unwrap();
+                Cow::Owned(encoder.finish().unwrap())
+            },
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+        match self {
+            ZipMode::Uncompressed => Ok(Cow::Borrowed(bytes)),
+            ZipMode::Compressed => {
+                let mut decoder = ZlibDecoder::new(Vec::new());
+                decoder.write_all(bytes)?;
+                Ok(Cow::Owned(decoder.finish()?))
+            },
+        }
+    }
+}
+
 macro_attr! {
     #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
     #[derive(Debug, N, EnumDisplay!, EnumFromStr!)]
@@ -203,6 +237,8 @@ impl FieldType {
             (_, _, CNAM, _) => FieldType::StringZ,
             (CELL, _, CNDT, _) => FieldType::Grid,
             (CONT, _, CNDT, _) => FieldType::F32,
+            (CREA, _, CNDT, _) => FieldType::StringZ,
+            (NPC_, _, CNDT, _) => FieldType::StringZ,
             (_, TIME, COUN, _) => FieldType::I64,
             (_, ANIS, COUN, _) => FieldType::I64,
             (_, _, COUN, _) => FieldType::I32,
@@ -487,6 +523,83 @@ impl FieldType {
             _ => FieldType::U8List
         }
     }
+    pub(crate) fn accepts(self, field: &Field) -> bool {
+        match self {
+            FieldType::U8List | FieldType::U8ListZip => matches!(field, Field::U8List(_)),
+            FieldType::String(_) => matches!(field, Field::String(_)),
+            FieldType::Multiline(_) => matches!(field, Field::StringList(_)),
+            FieldType::MarkerU8(_) => matches!(field, Field::None),
+            FieldType::Bool8 | FieldType::Bool32 => matches!(field, Field::Bool(_)),
+            FieldType::DialogMetadata => matches!(field, Field::DialogType(_) | Field::I32(_)),
+            FieldType::PosRotOrCell => matches!(field, Field::PosRot(_) | Field::Cell(_)),
+            FieldType::RepairItem => matches!(field, Field::Tool(_)),
+            FieldType::Ingredient => matches!(field, Field::Ingredient(_)),
+            FieldType::ScriptMetadata => matches!(field, Field::ScriptMetadata(_)),
+            FieldType::FileMetadata => matches!(field, Field::FileMetadata(_)),
+            FieldType::Npc => matches!(field, Field::Npc(_)),
+            FieldType::NpcState => matches!(field, Field::NpcState(_)),
+            FieldType::Effect => matches!(field, Field::Effect(_)),
+            FieldType::Spell => matches!(field, Field::Spell(_)),
+            FieldType::Ai => matches!(field, Field::Ai(_)),
+            FieldType::AiWander => matches!(field, Field::AiWander(_)),
+            FieldType::AiTravel => matches!(field, Field::AiTravel(_)),
+            FieldType::AiTarget => matches!(field, Field::AiTarget(_)),
+            FieldType::AiActivate => matches!(field, Field::AiActivate(_)),
+            FieldType::NpcFlags => matches!(field, Field::NpcFlags(_)),
+            FieldType::CreatureFlags => matches!(field, Field::CreatureFlags(_)),
+            FieldType::Book => matches!(field, Field::Book(_)),
+            FieldType::ContainerFlags => matches!(field, Field::ContainerFlags(_)),
+            FieldType::Creature => matches!(field, Field::Creature(_)),
+            FieldType::Light => matches!(field, Field::Light(_)),
+            FieldType::MiscItem => matches!(field, Field::MiscItem(_)),
+            FieldType::Apparatus => matches!(field, Field::Apparatus(_)),
+            FieldType::Weapon => matches!(field, Field::Weapon(_)),
+            FieldType::Armor => matches!(field, Field::Armor(_)),
+            FieldType::BipedObject => matches!(field, Field::BipedObject(_)),
+            FieldType::BodyPart => matches!(field, Field::BodyPart(_)),
+            FieldType::Clothing => matches!(field, Field::Clothing(_)),
+            FieldType::Enchantment => matches!(field, Field::Enchantment(_)),
+            FieldType::Tool => matches!(field, Field::Tool(_)),
+            FieldType::Pos => matches!(field, Field::Pos(_)),
+            FieldType::PosRot => matches!(field, Field::PosRot(_)),
+            FieldType::Grid => matches!(field, Field::Grid(_)),
+            FieldType::PathGrid => matches!(field, Field::PathGrid(_)),
+            FieldType::ScriptVars => matches!(field, Field::ScriptVars(_)),
+            FieldType::I16List => matches!(field, Field::I16List(_)),
+            FieldType::I32List => matches!(field, Field::I32List(_)),
+            FieldType::F32List => matches!(field, Field::F32List(_)),
+            FieldType::Weather => matches!(field, Field::Weather(_)),
+            FieldType::Color => matches!(field, Field::Color(_)),
+            FieldType::SoundChance => matches!(field, Field::SoundChance(_)),
+            FieldType::Potion => matches!(field, Field::Potion(_)),
+            FieldType::Class => matches!(field, Field::Class(_)),
+            FieldType::Skill => matches!(field, Field::Skill(_)),
+            FieldType::EffectIndex => matches!(field, Field::EffectIndex(_)),
+            FieldType::Item => matches!(field, Field::Item(_)),
+            FieldType::Sound => matches!(field, Field::Sound(_)),
+            FieldType::EffectMetadata => matches!(field, Field::EffectMetadata(_)),
+            FieldType::Race => matches!(field, Field::Race(_)),
+            FieldType::SoundGen => matches!(field, Field::SoundGen(_)),
+            FieldType::Info => matches!(field, Field::Info(_)),
+            FieldType::Faction => matches!(field, Field::Faction(_)),
+            FieldType::SkillMetadata => matches!(field, Field::SkillMetadata(_)),
+            FieldType::Interior => matches!(field, Field::Interior(_)),
+            FieldType::CurrentTime => matches!(field, Field::CurrentTime(_)),
+            FieldType::Time => matches!(field, Field::Time(_)),
+            FieldType::EffectArg => matches!(field, Field::EffectArg(_)),
+            FieldType::Attributes => matches!(field, Field::Attributes(_)),
+            FieldType::Skills => matches!(field, Field::Skills(_)),
+            FieldType::Tag => matches!(field, Field::Tag(_)),
+            FieldType::ScriptData => matches!(field, Field::ScriptData(_)),
+            FieldType::StringZ => matches!(field, Field::StringZ(_)),
+            FieldType::StringZList => matches!(field, Field::StringZList(_)),
+            FieldType::F32 => matches!(field, Field::F32(_)),
+            FieldType::I32 => matches!(field, Field::I32(_)),
+            FieldType::I16 => matches!(field, Field::I16(_)),
+            FieldType::I64 => matches!(field, Field::I64(_)),
+            FieldType::U8 => matches!(field, Field::U8(_)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -3265,7 +3378,7 @@ pub struct Interior {
     pub fog_density: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct Grid {
     pub x: i32,
     pub y: i32,
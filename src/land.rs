@@ -0,0 +1,261 @@
+use crate::field::*;
+use crate::record::*;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+pub const LAND_VERTICES: usize = 65;
+
+pub const LAND_TEXTURES: usize = 16;
+
+pub const LAND_HEIGHT_SCALE: f32 = 8.0;
+
+#[derive(Debug)]
+pub enum LandError {
+    Zip(io::Error),
+    UnexpectedFieldType(Tag),
+    UnexpectedSize { field_tag: Tag, expected: usize, actual: usize },
+    HeightDelta { x: usize, y: usize },
+}
+
+impl Display for LandError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LandError::Zip(e) => write!(f, "invalid compressed data: {e}"),
+            LandError::UnexpectedFieldType(field_tag) => write!(f, "{field_tag} field should have byte list type"),
+            LandError::UnexpectedSize { field_tag, expected, actual } =>
+                write!(f, "{field_tag} field size mismatch: {expected} expected, {actual} found"),
+            LandError::HeightDelta { x, y } => write!(f, "height delta at ({x}, {y}) is not representable in VHGT"),
+        }
+    }
+}
+
+impl Error for LandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        if let LandError::Zip(e) = self { Some(e) } else { None }
+    }
+}
+
+fn land_bytes(field_tag: Tag, field: &Field, zip_mode: ZipMode, expected: usize) -> Result<Vec<u8>, LandError> {
+    let Field::U8List(bytes) = field else { return Err(LandError::UnexpectedFieldType(field_tag)); };
+    let bytes = zip_mode.decode(bytes).map_err(LandError::Zip)?;
+    check_len(field_tag, expected, bytes.len())?;
+    Ok(bytes.into_owned())
+}
+
+fn check_len(field_tag: Tag, expected: usize, actual: usize) -> Result<(), LandError> {
+    if actual != expected {
+        return Err(LandError::UnexpectedSize { field_tag, expected, actual });
+    }
+    Ok(())
+}
+
+fn land_field(bytes: &[u8], zip_mode: ZipMode) -> Field {
+    Field::U8List(zip_mode.encode(bytes).into_owned())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LandHeights {
+    pub offset: f32,
+    pub heights: Vec<f32>,
+    pub padding: [u8; 3],
+}
+
+impl LandHeights {
+    pub const SIZE: usize = 4 + LAND_VERTICES * LAND_VERTICES + 3;
+
+    pub fn from_field(field: &Field, zip_mode: ZipMode) -> Result<Self, LandError> {
+        let bytes = land_bytes(VHGT, field, zip_mode, Self::SIZE)?;
+        let offset = f32::from_le_bytes(bytes[.. 4].try_into().unwrap()) * LAND_HEIGHT_SCALE;
+        let deltas = &bytes[4 .. 4 + LAND_VERTICES * LAND_VERTICES];
+        let mut heights = Vec::with_capacity(LAND_VERTICES * LAND_VERTICES);
+        let mut row_height = offset;
+        for row in deltas.chunks(LAND_VERTICES) {
+            row_height += row[0] as i8 as f32 * LAND_HEIGHT_SCALE;
+            let mut height = row_height;
+            heights.push(height);
+            for &delta in &row[1 ..] {
+                height += delta as i8 as f32 * LAND_HEIGHT_SCALE;
+                heights.push(height);
+            }
+        }
+        Ok(LandHeights { offset, heights, padding: bytes[Self::SIZE - 3 ..].try_into().unwrap() })
+    }
+
+    pub fn to_field(&self, zip_mode: ZipMode) -> Result<Field, LandError> {
+        check_len(VHGT, LAND_VERTICES * LAND_VERTICES, self.heights.len())?;
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&(self.offset / LAND_HEIGHT_SCALE).to_le_bytes());
+        let delta = |from: f32, to: f32, x: usize, y: usize| {
+            let delta = (to - from) / LAND_HEIGHT_SCALE;
+            if delta.fract() != 0.0 || delta < i8::MIN as f32 || delta > i8::MAX as f32 {
+                return Err(LandError::HeightDelta { x, y });
+            }
+            Ok(delta as i8 as u8)
+        };
+        let mut row_height = self.offset;
+        for (y, row) in self.heights.chunks(LAND_VERTICES).enumerate() {
+            bytes.push(delta(row_height, row[0], 0, y)?);
+            row_height = row[0];
+            for x in 1 .. row.len() {
+                bytes.push(delta(row[x - 1], row[x], x, y)?);
+            }
+        }
+        bytes.extend_from_slice(&self.padding);
+        Ok(land_field(&bytes, zip_mode))
+    }
+
+    pub fn height(&self, x: usize, y: usize) -> f32 { self.heights[y * LAND_VERTICES + x] }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LandNormals {
+    pub normals: Vec<[i8; 3]>,
+}
+
+impl LandNormals {
+    pub const SIZE: usize = LAND_VERTICES * LAND_VERTICES * 3;
+
+    pub fn from_field(field: &Field, zip_mode: ZipMode) -> Result<Self, LandError> {
+        let bytes = land_bytes(VNML, field, zip_mode, Self::SIZE)?;
+        let normals = bytes.chunks(3).map(|x| [x[0] as i8, x[1] as i8, x[2] as i8]).collect();
+        Ok(LandNormals { normals })
+    }
+
+    pub fn to_field(&self, zip_mode: ZipMode) -> Result<Field, LandError> {
+        check_len(VNML, LAND_VERTICES * LAND_VERTICES, self.normals.len())?;
+        let bytes = self.normals.iter().flat_map(|x| x.map(|c| c as u8)).collect::<Vec<_>>();
+        Ok(land_field(&bytes, zip_mode))
+    }
+
+    pub fn normal(&self, x: usize, y: usize) -> [i8; 3] { self.normals[y * LAND_VERTICES + x] }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LandColors {
+    pub colors: Vec<Color>,
+}
+
+impl LandColors {
+    pub const SIZE: usize = LAND_VERTICES * LAND_VERTICES * 3;
+
+    pub fn from_field(field: &Field, zip_mode: ZipMode) -> Result<Self, LandError> {
+        let bytes = land_bytes(VCLR, field, zip_mode, Self::SIZE)?;
+        let colors = bytes.chunks(3).map(|x| Color { r: x[0], g: x[1], b: x[2] }).collect();
+        Ok(LandColors { colors })
+    }
+
+    pub fn to_field(&self, zip_mode: ZipMode) -> Result<Field, LandError> {
+        check_len(VCLR, LAND_VERTICES * LAND_VERTICES, self.colors.len())?;
+        let bytes = self.colors.iter().flat_map(|x| [x.r, x.g, x.b]).collect::<Vec<_>>();
+        Ok(land_field(&bytes, zip_mode))
+    }
+
+    pub fn color(&self, x: usize, y: usize) -> Color { self.colors[y * LAND_VERTICES + x] }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LandTextures {
+    pub textures: Vec<u16>,
+}
+
+fn swizzled_texture_index(index: usize) -> usize {
+    let (block, cell) = (index / 16, index % 16);
+    let x = (block % 4) * 4 + cell % 4;
+    let y = (block / 4) * 4 + cell / 4;
+    y * LAND_TEXTURES + x
+}
+
+impl LandTextures {
+    pub const SIZE: usize = LAND_TEXTURES * LAND_TEXTURES * 2;
+
+    pub fn from_field(field: &Field, zip_mode: ZipMode) -> Result<Self, LandError> {
+        let bytes = land_bytes(VTEX, field, zip_mode, Self::SIZE)?;
+        let mut textures = vec![0; LAND_TEXTURES * LAND_TEXTURES];
+        for (index, texture) in bytes.chunks(2).enumerate() {
+            textures[swizzled_texture_index(index)] = u16::from_le_bytes([texture[0], texture[1]]);
+        }
+        Ok(LandTextures { textures })
+    }
+
+    pub fn to_field(&self, zip_mode: ZipMode) -> Result<Field, LandError> {
+        check_len(VTEX, LAND_TEXTURES * LAND_TEXTURES, self.textures.len())?;
+        let bytes = (0 .. LAND_TEXTURES * LAND_TEXTURES)
+            .flat_map(|index| self.textures[swizzled_texture_index(index)].to_le_bytes())
+            .collect::<Vec<_>>();
+        Ok(land_field(&bytes, zip_mode))
+    }
+
+    pub fn texture(&self, x: usize, y: usize) -> u16 { self.textures[y * LAND_TEXTURES + x] }
+}
+
+impl Record {
+    fn land_field(&self, tag: Tag) -> Option<&Field> {
+        if self.tag != LAND { return None; }
+        self.fields.iter().find(|x| x.0 == tag).map(|x| &x.1)
+    }
+
+    pub fn land_heights(&self, zip_mode: ZipMode) -> Option<Result<LandHeights, LandError>> {
+        self.land_field(VHGT).map(|x| LandHeights::from_field(x, zip_mode))
+    }
+
+    pub fn land_normals(&self, zip_mode: ZipMode) -> Option<Result<LandNormals, LandError>> {
+        self.land_field(VNML).map(|x| LandNormals::from_field(x, zip_mode))
+    }
+
+    pub fn land_colors(&self, zip_mode: ZipMode) -> Option<Result<LandColors, LandError>> {
+        self.land_field(VCLR).map(|x| LandColors::from_field(x, zip_mode))
+    }
+
+    pub fn land_textures(&self, zip_mode: ZipMode) -> Option<Result<LandTextures, LandError>> {
+        self.land_field(VTEX).map(|x| LandTextures::from_field(x, zip_mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn land_heights_round_trip() {
+        let mut bytes = 100.0f32.to_le_bytes().to_vec();
+        bytes.extend((0 .. LAND_VERTICES * LAND_VERTICES).map(|i| if i % LAND_VERTICES == 0 { 0xFF } else { 2 }));
+        bytes.extend([0, 1, 2]);
+        let field = Field::U8List(bytes);
+        let heights = LandHeights::from_field(&field, ZipMode::Uncompressed).unwrap();
+        assert_eq!(heights.offset, 800.0);
+        assert_eq!(heights.height(0, 0), 792.0);
+        assert_eq!(heights.height(64, 0), 1816.0);
+        assert_eq!(heights.height(0, 1), 784.0);
+        assert_eq!(heights.to_field(ZipMode::Uncompressed).unwrap(), field);
+        let mut uneven = heights.clone();
+        uneven.heights[3] += 4.0;
+        assert!(matches!(uneven.to_field(ZipMode::Uncompressed), Err(LandError::HeightDelta { x: 3, y: 0 })));
+        let mut steep = heights.clone();
+        steep.heights[3] += 1600.0;
+        assert!(matches!(steep.to_field(ZipMode::Uncompressed), Err(LandError::HeightDelta { x: 3, y: 0 })));
+        steep.heights.push(0.0);
+        assert!(matches!(steep.to_field(ZipMode::Uncompressed), Err(LandError::UnexpectedSize { field_tag: VHGT, .. })));
+    }
+
+    #[test]
+    fn land_textures_swizzle() {
+        let bytes = (0 .. 256u16).flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+        let record = Record {
+            tag: LAND,
+            flags: RecordFlags::empty(),
+            fields: vec![(VTEX, Field::U8List(ZipMode::Compressed.encode(&bytes).into_owned()))]
+        };
+        let textures = record.land_textures(ZipMode::Compressed).unwrap().unwrap();
+        assert_eq!(textures.texture(3, 0), 3);
+        assert_eq!(textures.texture(4, 0), 16);
+        assert_eq!(textures.texture(0, 1), 4);
+        assert_eq!(textures.texture(15, 15), 255);
+        assert_eq!(textures.to_field(ZipMode::Compressed).unwrap(), record.fields[0].1);
+        let short = LandTextures { textures: vec![0; 255] };
+        assert!(matches!(
+            short.to_field(ZipMode::Compressed),
+            Err(LandError::UnexpectedSize { field_tag: VTEX, expected: 256, actual: 255 })
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+use crate::field::*;
+use crate::record::*;
+use crate::strings::*;
+use crate::view::*;
+use std::collections::HashMap;
+
+bitflags_ext! {
+    pub struct LeveledListFlags: u32 {
+        ALL_LEVELS = 0x01,
+        EACH_ITEM = 0x02
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LeveledList {
+    pub tag: Tag,
+    pub record_flags: RecordFlags,
+    pub id: StringZ,
+    pub deleted: bool,
+    pub flags: LeveledListFlags,
+    pub chance_none: u8,
+    pub entries: Vec<(String, u16)>,
+}
+
+impl LeveledList {
+    fn entry_tag(tag: Tag) -> Tag { if tag == LEVC { CNAM } else { INAM } }
+
+    fn has_body(&self) -> bool {
+        !self.deleted || !self.flags.is_empty() || self.chance_none != 0 || !self.entries.is_empty()
+    }
+}
+
+impl TryFrom<&Record> for LeveledList {
+    type Error = RecordViewError;
+
+    fn try_from(record: &Record) -> Result<Self, Self::Error> {
+        if record.tag != LEVI && record.tag != LEVC {
+            return Err(RecordViewError::TagsMismatch { expected: &[LEVI, LEVC], actual: record.tag });
+        }
+        let entry_tag = LeveledList::entry_tag(record.tag);
+        let mut fields = record.fields.iter().enumerate();
+        let mut next = |expected: Tag| {
+            let (field_index, (field_tag, field)) = fields.next().ok_or(RecordViewError::UnexpectedField {
+                record_tag: record.tag, field_tag: expected, field_index: record.fields.len()
+            })?;
+            if *field_tag != expected {
+                return Err(RecordViewError::UnexpectedField { record_tag: record.tag, field_tag: *field_tag, field_index });
+            }
+            Ok((field_index, field))
+        };
+        let unexpected = |field_index: usize| RecordViewError::UnexpectedField {
+            record_tag: record.tag, field_tag: record.fields[field_index].0, field_index
+        };
+        let id = match next(NAME)? {
+            (_, Field::StringZ(v)) => v.clone(),
+            (field_index, _) => return Err(unexpected(field_index)),
+        };
+        let deleted = match record.fields.get(1) {
+            Some((DELE, Field::I32(0))) => { next(DELE)?; true },
+            Some((DELE, _)) => return Err(unexpected(1)),
+            _ => false
+        };
+        if deleted && record.fields.len() == 2 {
+            return Ok(LeveledList {
+                tag: record.tag, record_flags: record.flags, id, deleted, flags: LeveledListFlags::empty(), chance_none: 0,
+                entries: Vec::new()
+            });
+        }
+        let flags = match next(DATA)? {
+            (_, &Field::I32(v)) => LeveledListFlags::from_bits_retain(v as u32),
+            (field_index, _) => return Err(unexpected(field_index)),
+        };
+        let chance_none = match next(NNAM)? {
+            (_, &Field::U8(v)) => v,
+            (field_index, _) => return Err(unexpected(field_index)),
+        };
+        let (count_index, count) = match next(INDX)? {
+            (field_index, &Field::I32(v)) => (field_index, v),
+            (field_index, _) => return Err(unexpected(field_index)),
+        };
+        if count < 0 {
+            return Err(unexpected(count_index));
+        }
+        let mut entries = Vec::with_capacity(count as usize);
+        while entries.len() < count as usize {
+            let entry = match next(entry_tag)? {
+                (_, Field::StringZ(v)) if v.has_tail_zero => v.string.clone(),
+                (field_index, _) => return Err(unexpected(field_index)),
+            };
+            let level = match next(INTV)? {
+                (_, &Field::I16(v)) => v as u16,
+                (field_index, _) => return Err(unexpected(field_index)),
+            };
+            entries.push((entry, level));
+        }
+        if let Some((field_index, _)) = fields.next() {
+            return Err(unexpected(field_index));
+        }
+        Ok(LeveledList { tag: record.tag, record_flags: record.flags, id, deleted, flags, chance_none, entries })
+    }
+}
+
+impl From<LeveledList> for Record {
+    fn from(list: LeveledList) -> Record {
+        let entry_tag = LeveledList::entry_tag(list.tag);
+        let has_body = list.has_body();
+        let mut fields = vec![(NAME, Field::StringZ(list.id))];
+        if list.deleted {
+            fields.push((DELE, Field::I32(0)));
+        }
+        if has_body {
+            fields.extend([
+                (DATA, Field::I32(list.flags.bits() as i32)),
+                (NNAM, Field::U8(list.chance_none)),
+                (INDX, Field::I32(list.entries.len() as i32)),
+            ]);
+        }
+        for (entry, level) in list.entries {
+            fields.push((entry_tag, Field::StringZ(entry.into())));
+            fields.push((INTV, Field::I16(level as i16)));
+        }
+        Record { tag: list.tag, flags: list.record_flags, fields }
+    }
+}
+
+fn entry_counts(entries: &[(String, u16)]) -> HashMap<(String, u16), usize> {
+    let mut counts = HashMap::new();
+    for (entry, level) in entries {
+        *counts.entry((entry.to_lowercase(), *level)).or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn merge_leveled_lists(base: &LeveledList, overrides: &[LeveledList]) -> LeveledList {
+    let mut merged = base.clone();
+    let base_counts = entry_counts(&base.entries);
+    let mut removed = HashMap::new();
+    let mut added = HashMap::new();
+    let mut added_order = Vec::new();
+    for list in overrides {
+        if list.record_flags != base.record_flags { merged.record_flags = list.record_flags; }
+        if list.deleted != base.deleted { merged.deleted = list.deleted; }
+        if list.deleted { continue; }
+        if list.flags != base.flags { merged.flags = list.flags; }
+        if list.chance_none != base.chance_none { merged.chance_none = list.chance_none; }
+        let counts = entry_counts(&list.entries);
+        for (key, &base_count) in &base_counts {
+            let count = counts.get(key).copied().unwrap_or(0);
+            if count < base_count {
+                let removed = removed.entry(key.clone()).or_insert(0);
+                *removed = (base_count - count).max(*removed);
+            }
+        }
+        for (entry, level) in &list.entries {
+            let key = (entry.to_lowercase(), *level);
+            let count = counts[&key].saturating_sub(base_counts.get(&key).copied().unwrap_or(0));
+            if count == 0 { continue; }
+            let added = added.entry(key).or_insert_with(|| {
+                added_order.push((entry.clone(), *level));
+                0
+            });
+            *added = count.max(*added);
+        }
+    }
+    merged.entries.retain(|(entry, level)| {
+        let Some(removed) = removed.get_mut(&(entry.to_lowercase(), *level)) else { return true; };
+        if *removed == 0 { return true; }
+        *removed -= 1;
+        false
+    });
+    for (entry, level) in added_order {
+        let count = added[&(entry.to_lowercase(), level)];
+        merged.entries.extend((0 .. count).map(|_| (entry.clone(), level)));
+    }
+    merged.entries.sort_by_key(|x| x.1);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn list(chance_none: u8, entries: &[(&str, u16)]) -> LeveledList {
+        LeveledList {
+            tag: LEVI,
+            record_flags: RecordFlags::empty(),
+            id: "random_gold".into(),
+            deleted: false,
+            flags: LeveledListFlags::ALL_LEVELS,
+            chance_none,
+            entries: entries.iter().map(|&(entry, level)| (entry.into(), level)).collect(),
+        }
+    }
+
+    #[test]
+    fn leveled_list_round_trip() {
+        let list = list(25, &[("gold_001", 1), ("gold_005", 5)]);
+        let record = Record::from(list.clone());
+        assert_eq!(record.fields.iter().map(|x| x.0).collect::<Vec<_>>(), vec![NAME, DATA, NNAM, INDX, INAM, INTV, INAM, INTV]);
+        assert_eq!(LeveledList::try_from(&record), Ok(list));
+        let mut invalid = record.clone();
+        invalid.fields.swap(4, 5);
+        assert_eq!(
+            LeveledList::try_from(&invalid),
+            Err(RecordViewError::UnexpectedField { record_tag: LEVI, field_tag: INTV, field_index: 4 })
+        );
+    }
+
+    #[test]
+    fn merge_leveled_list_changes() {
+        let base = list(0, &[("gold_001", 1), ("gold_005", 5), ("gold_010", 10)]);
+        let a = list(0, &[("gold_001", 1), ("Gold_005", 5), ("gold_010", 10), ("gold_025", 20)]);
+        let b = list(50, &[("gold_001", 1), ("gold_010", 10), ("gold_100", 3)]);
+        let merged = merge_leveled_lists(&base, &[a, b]);
+        assert_eq!(merged, list(50, &[("gold_001", 1), ("gold_100", 3), ("gold_010", 10), ("gold_025", 20)]));
+        let a = list(0, &[("gold_001", 1), ("gold_005", 5), ("gold_010", 10), ("gold_025", 20), ("gold_025", 20)]);
+        let b = list(0, &[("gold_001", 1), ("gold_005", 5), ("gold_010", 10), ("gold_025", 20), ("gold_100", 30)]);
+        let merged = merge_leveled_lists(&base, &[a, b]);
+        assert_eq!(merged.entries.iter().filter(|x| x.0 == "gold_025").count(), 2);
+        assert_eq!(merged.entries.len(), 6);
+    }
+
+    #[test]
+    fn deleted_leveled_list() {
+        let record = Record {
+            tag: LEVI,
+            flags: RecordFlags::empty(),
+            fields: vec![(NAME, Field::StringZ("random_gold".into())), (DELE, Field::I32(0))]
+        };
+        let deleted = LeveledList::try_from(&record).unwrap();
+        assert!(deleted.deleted);
+        assert_eq!(Record::from(deleted.clone()), record);
+        let base = list(0, &[("gold_001", 1)]);
+        let merged = merge_leveled_lists(&base, &[list(0, &[("gold_001", 1), ("gold_005", 5)]), deleted]);
+        assert!(merged.deleted);
+        assert_eq!(merged.entries.len(), 2);
+    }
+}
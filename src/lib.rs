@@ -47,8 +47,40 @@ mod record;
 
 pub use crate::record::*;
 
+mod view;
+
+pub use crate::view::*;
+
+mod land;
+
+pub use crate::land::*;
+
+mod path_grid;
+
+pub use crate::path_grid::*;
+
+mod cell;
+
+pub use crate::cell::*;
+
+mod ai;
+
+pub use crate::ai::*;
+
+mod leveled;
+
+pub use crate::leveled::*;
+
 pub mod read;
 
+pub mod write;
+
+pub mod load_order;
+
+pub mod diff;
+
+pub mod merge;
+
 mod strings;
 
 pub use crate::strings::*;
@@ -164,7 +196,7 @@ mod tests {
         let mut res = Vec::new();
         for (is_last, record) in file.iter().identify_last() {
             code::serialize_into_vec(
-                &ValueWithSeed(record, RecordSerde { code_page: Some(CodePage::Russian), omwsave: false }), &mut res, isolated && is_last
+                &ValueWithSeed(record, RecordSerde { code_page: Some(CodePage::Russian), omwsave: false, zip_mode: ZipMode::Compressed }), &mut res, isolated && is_last
             )?;
         }
         Ok(res)
@@ -185,7 +217,7 @@ mod tests {
     }
 
     fn deserialize_record(bytes: &mut &[u8], isolated: bool) -> Result<Record, code::de::Error> {
-        code::deserialize_from_slice_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false }, bytes, isolated)
+        code::deserialize_from_slice_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false, zip_mode: ZipMode::Compressed }, bytes, isolated)
     }
 
     #[test]
@@ -330,7 +362,7 @@ mod tests {
         let mut records = Records::new(CodePage::Russian, RecordReadMode::Strict, false, 0, &mut bytes);
         let record = records.next().unwrap().unwrap();
         assert_eq!(record.fields[1].1, Field::StringZ(StringZ::from("Редгард")));
-        let yaml = serde_yaml::to_string(&ValueWithSeed(&record, RecordSerde { code_page: None, omwsave: false })).unwrap();
+        let yaml = serde_yaml::to_string(&ValueWithSeed(&record, RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed })).unwrap();
         assert!(!yaml.contains('^'));
         assert!(!yaml.contains("\\u"));
     }
@@ -352,7 +384,7 @@ mod tests {
                 }))
             ]
         };
-        let bytes = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false }), false).unwrap();
+        let bytes = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false, zip_mode: ZipMode::Compressed }), false).unwrap();
         let read = {
             let mut bytes = &bytes[..];
             let mut records = Records::new(CodePage::Russian, RecordReadMode::Strict, false, 0, &mut bytes);
@@ -361,7 +393,7 @@ mod tests {
             read
         };
         assert_eq!(record, read);
-        let deserialized: Record = code::deserialize_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false }, &bytes, false)
+        let deserialized: Record = code::deserialize_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false, zip_mode: ZipMode::Compressed }, &bytes, false)
             .unwrap();
         assert_eq!(record, deserialized);
     }
@@ -381,7 +413,7 @@ mod tests {
                 speed: 1.0
             }))]
         };
-        let bytes = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false }), false).unwrap();
+        let bytes = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false, zip_mode: ZipMode::Compressed }), false).unwrap();
         let read = {
             let mut bytes = &bytes[..];
             let mut records = Records::new(CodePage::Russian, RecordReadMode::Strict, false, 0, &mut bytes);
@@ -390,7 +422,7 @@ mod tests {
             read
         };
         assert_eq!(record, read);
-        let deserialized: Record = code::deserialize_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false }, &bytes, false)
+        let deserialized: Record = code::deserialize_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false, zip_mode: ZipMode::Compressed }, &bytes, false)
             .unwrap();
         assert_eq!(record, deserialized);
     }
@@ -404,7 +436,7 @@ mod tests {
                 "\0\0\0".into(),
             ]))]
         };
-        let bytes = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false }), false).unwrap();
+        let bytes = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false, zip_mode: ZipMode::Compressed }), false).unwrap();
         let read = {
             let mut bytes = &bytes[..];
             let mut records = Records::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut bytes);
@@ -413,7 +445,7 @@ mod tests {
             read
         };
         assert_eq!(record, read);
-        let deserialized = code::deserialize_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false }, &bytes, false).unwrap();
+        let deserialized = code::deserialize_seed(RecordSerde { code_page: Some(CodePage::Russian), omwsave: false, zip_mode: ZipMode::Compressed }, &bytes, false).unwrap();
         assert_eq!(record, deserialized);
     }
 
@@ -428,7 +460,7 @@ mod tests {
   - FLTV: 0.1
   - FLTV: -0.0
 ";
-        let res: Vec<Record> = VecSerde(RecordSerde { code_page: None, omwsave: false }).deserialize(serde_yaml::Deserializer::from_str(yaml)).unwrap();
+        let res: Vec<Record> = VecSerde(RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }).deserialize(serde_yaml::Deserializer::from_str(yaml)).unwrap();
         assert_eq!(res.len(), 1);
         assert_eq!(res[0].fields.len(), 5);
         assert_eq!(res[0].fields[0].1, Field::F32(3.0));
@@ -441,7 +473,7 @@ mod tests {
         assert_eq!(res[0].fields[2].1, Field::F32(custom_nan));
         assert_eq!(res[0].fields[3].1, Field::F32(0.1));
         assert_eq!(res[0].fields[4].1, Field::F32(0.0_f32.copysign(-1.0)));
-        let res_yaml = serde_yaml::to_string(&ValueWithSeed(&res[..], VecSerde(RecordSerde { code_page: None, omwsave: false }))).unwrap();
+        let res_yaml = serde_yaml::to_string(&ValueWithSeed(&res[..], VecSerde(RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }))).unwrap();
         assert_eq!(res_yaml, yaml);
     }
 
@@ -458,7 +490,7 @@ mod tests {
       color: '#F58C28'
       flags: DYNAMIC CAN_CARRY FIRE FLICKER_SLOW
 ";
-        let res: Vec<Record> = VecSerde(RecordSerde { code_page: None, omwsave: false }).deserialize(serde_yaml::Deserializer::from_str(yaml)).unwrap();
+        let res: Vec<Record> = VecSerde(RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }).deserialize(serde_yaml::Deserializer::from_str(yaml)).unwrap();
         assert_eq!(res.len(), 1);
         assert_eq!(res[0].fields.len(), 1);
         if let Field::Light(field) = &res[0].fields[0].1 {
@@ -466,7 +498,7 @@ mod tests {
         } else {
             panic!()
         }
-        let res_yaml = serde_yaml::to_string(&ValueWithSeed(&res[..], VecSerde(RecordSerde { code_page: None, omwsave: false }))).unwrap();
+        let res_yaml = serde_yaml::to_string(&ValueWithSeed(&res[..], VecSerde(RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }))).unwrap();
         assert_eq!(res_yaml, yaml);
     }
 }
@@ -0,0 +1,120 @@
+use crate::code_page::CodePage;
+use crate::field::*;
+use crate::read::*;
+use crate::record::*;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io::Read;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResolvedRecord {
+    pub record: Record,
+    pub plugin: usize,
+    pub overridden: Vec<usize>,
+}
+
+impl ResolvedRecord {
+    pub fn is_deleted(&self) -> bool { self.record.flags.contains(RecordFlags::DELETED) }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadOrder {
+    plugins: Vec<String>,
+    records: Vec<ResolvedRecord>,
+    index: HashMap<(Tag, RecordId), usize>,
+}
+
+impl LoadOrder {
+    pub fn new() -> Self { LoadOrder::default() }
+
+    pub fn plugins(&self) -> &[String] { &self.plugins }
+
+    pub fn add_records(&mut self, plugin: impl Into<String>, records: impl IntoIterator<Item=Record>) -> usize {
+        let plugin_index = self.plugins.len();
+        self.plugins.push(plugin.into());
+        for record in records {
+            if record.tag == TES3 { continue; }
+            let Some(id) = record.id() else {
+                self.records.push(ResolvedRecord { record, plugin: plugin_index, overridden: Vec::new() });
+                continue;
+            };
+            match self.index.entry((record.tag, id)) {
+                Entry::Occupied(entry) => {
+                    let resolved = &mut self.records[*entry.get()];
+                    resolved.overridden.push(resolved.plugin);
+                    resolved.record = record;
+                    resolved.plugin = plugin_index;
+                },
+                Entry::Vacant(entry) => {
+                    entry.insert(self.records.len());
+                    self.records.push(ResolvedRecord { record, plugin: plugin_index, overridden: Vec::new() });
+                },
+            }
+        }
+        plugin_index
+    }
+
+    pub fn add_plugin<Input: Read + ?Sized>(
+        &mut self,
+        plugin: impl Into<String>,
+        code_page: CodePage,
+        mode: RecordReadMode,
+        zip_mode: ZipMode,
+        input: &mut Input
+    ) -> Result<usize, ReadRecordError> {
+        let records = Records::new(code_page, mode, false, 0, input).with_zip_mode(zip_mode).collect::<Result<Vec<_>, _>>()?;
+        Ok(self.add_records(plugin, records))
+    }
+
+    pub fn get(&self, tag: Tag, id: &RecordId) -> Option<&ResolvedRecord> {
+        let index = *self.index.get(&(tag, id.clone()))?;
+        let resolved = &self.records[index];
+        if resolved.is_deleted() { None } else { Some(resolved) }
+    }
+
+    pub fn records(&self) -> impl Iterator<Item=&ResolvedRecord> {
+        self.records.iter().filter(|x| !x.is_deleted())
+    }
+
+    pub fn all_records(&self) -> &[ResolvedRecord] { &self.records }
+
+    pub fn into_records(self) -> Vec<Record> {
+        self.records.into_iter().filter(|x| !x.is_deleted()).map(|x| x.record).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::load_order::*;
+    use std::str::FromStr;
+
+    fn misc(id: &str, name: &str, flags: RecordFlags) -> Record {
+        Record {
+            tag: Tag::from_str("MISC").unwrap(),
+            flags,
+            fields: vec![
+                (NAME, Field::StringZ(id.into())),
+                (FNAM, Field::StringZ(name.into())),
+            ]
+        }
+    }
+
+    #[test]
+    fn later_plugins_win() {
+        let mut load_order = LoadOrder::new();
+        load_order.add_records("Morrowind.esm", [
+            misc("gold_001", "Gold", RecordFlags::empty()),
+            misc("misc_com_bucket_01", "Bucket", RecordFlags::empty()),
+        ]);
+        load_order.add_records("a.esp", [misc("Gold_001", "Septim", RecordFlags::empty())]);
+        load_order.add_records("b.esp", [misc("misc_com_bucket_01", "Bucket", RecordFlags::DELETED)]);
+        let gold = load_order.get(Tag::from_str("MISC").unwrap(), &RecordId::Name("gold_001".into())).unwrap();
+        assert_eq!(gold.plugin, 1);
+        assert_eq!(gold.overridden, vec![0]);
+        assert_eq!(gold.record.fields[1].1, Field::StringZ("Septim".into()));
+        assert!(load_order.get(Tag::from_str("MISC").unwrap(), &RecordId::Name("misc_com_bucket_01".into())).is_none());
+        assert_eq!(load_order.records().count(), 1);
+        assert_eq!(load_order.all_records().len(), 2);
+    }
+}
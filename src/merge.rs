@@ -0,0 +1,353 @@
+use crate::cell::*;
+use crate::diff::*;
+use crate::field::*;
+use crate::record::*;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Conflict {
+    Removed { side: Side, base: Record, modified: Record },
+    Flags { base: Option<RecordFlags>, ours: RecordFlags, theirs: RecordFlags },
+    Field { tag: Tag, occurrence: usize, base: Option<Field>, ours: Option<Field>, theirs: Option<Field> },
+    ReferenceRemoved { ref_num: Option<i32>, side: Side, base: CellReference, modified: CellReference },
+    ReferenceField {
+        ref_num: Option<i32>, tag: Tag, occurrence: usize, base: Option<Field>, ours: Option<Field>, theirs: Option<Field>
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MergeConflict {
+    pub tag: Tag,
+    pub id: Option<RecordId>,
+    pub conflict: Conflict,
+}
+
+fn merge_lists<K: Eq + Hash + Clone, V, R>(
+    base: Vec<(K, V)>, ours: Vec<(K, V)>, theirs: Vec<(K, V)>,
+    mut merge: impl FnMut(&K, Option<V>, Option<V>, Option<V>) -> Option<R>
+) -> Vec<R> {
+    let ours_keys = ours.iter().map(|x| x.0.clone()).collect::<HashSet<_>>();
+    let mut buckets: HashMap<Option<K>, Vec<K>> = HashMap::new();
+    let mut anchor = None;
+    for (key, _) in &theirs {
+        if ours_keys.contains(key) {
+            anchor = Some(key.clone());
+        } else {
+            buckets.entry(anchor.clone()).or_default().push(key.clone());
+        }
+    }
+    let mut order = buckets.remove(&None).unwrap_or_default();
+    for (key, _) in &ours {
+        order.push(key.clone());
+        order.extend(buckets.remove(&Some(key.clone())).unwrap_or_default());
+    }
+    let mut base = base.into_iter().collect::<HashMap<_, _>>();
+    let mut ours = ours.into_iter().collect::<HashMap<_, _>>();
+    let mut theirs = theirs.into_iter().collect::<HashMap<_, _>>();
+    order.into_iter().filter_map(|key| {
+        merge(&key, base.remove(&key), ours.remove(&key), theirs.remove(&key))
+    }).collect()
+}
+
+fn pair_changed(
+    reference: &[(Tag, Field)], fields: &[(Tag, Field)],
+    removed: &mut Vec<usize>, inserted: &mut Vec<usize>, aligned: &mut [Option<usize>]
+) {
+    for &i in removed.iter() {
+        if let Some(k) = inserted.iter().position(|&j| fields[j].0 == reference[i].0) {
+            aligned[inserted.remove(k)] = Some(i);
+        }
+    }
+    removed.clear();
+    inserted.clear();
+}
+
+fn field_keys(reference: &[(Tag, Field)], fields: &[(Tag, Field)], side: Side) -> Vec<((Tag, usize, Option<Side>), Field)> {
+    let mut aligned = vec![None; fields.len()];
+    let mut removed = Vec::new();
+    let mut inserted = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for op in lcs_ops(reference, fields) {
+        match op {
+            Op::Keep => {
+                pair_changed(reference, fields, &mut removed, &mut inserted, &mut aligned);
+                aligned[j] = Some(i);
+                i += 1;
+                j += 1;
+            },
+            Op::Remove => {
+                removed.push(i);
+                i += 1;
+            },
+            Op::Insert => {
+                inserted.push(j);
+                j += 1;
+            },
+        }
+    }
+    pair_changed(reference, fields, &mut removed, &mut inserted, &mut aligned);
+    let occurrences = |fields: &[(Tag, Field)]| {
+        let mut nth = HashMap::new();
+        fields.iter().map(|(tag, _)| {
+            let n = nth.entry(*tag).or_insert(0);
+            *n += 1;
+            *n - 1
+        }).collect::<Vec<_>>()
+    };
+    let reference_occurrences = occurrences(reference);
+    let field_occurrences = occurrences(fields);
+    fields.iter().zip(aligned).enumerate().map(|(j, ((tag, field), aligned))| {
+        let key = match aligned {
+            Some(i) => (*tag, reference_occurrences[i], None),
+            None => (*tag, field_occurrences[j], Some(side)),
+        };
+        (key, field.clone())
+    }).collect()
+}
+
+fn merge_fields(
+    base: Option<&[(Tag, Field)]>, ours: &[(Tag, Field)], theirs: &[(Tag, Field)],
+    mut conflict: impl FnMut(Tag, usize, Option<Field>, Option<Field>, Option<Field>)
+) -> Vec<(Tag, Field)> {
+    let reference = base.unwrap_or(ours);
+    merge_lists(
+        base.map_or_else(Vec::new, |x| field_keys(reference, x, Side::Ours)),
+        field_keys(reference, ours, Side::Ours), field_keys(reference, theirs, Side::Theirs),
+        |&(tag, occurrence, _), base, ours, theirs| {
+            if ours == theirs || base == theirs {
+                ours.map(|x| (tag, x))
+            } else if base == ours {
+                theirs.map(|x| (tag, x))
+            } else {
+                let merged = ours.clone().map(|x| (tag, x));
+                conflict(tag, occurrence, base, ours, theirs);
+                merged
+            }
+        }
+    )
+}
+
+fn merge_header(base: Option<&Record>, ours: &Record, theirs: &Record) -> (Record, Vec<Conflict>) {
+    let mut conflicts = Vec::new();
+    let flags = match base.map(|x| x.flags) {
+        _ if ours.flags == theirs.flags => ours.flags,
+        Some(base) if base == ours.flags => theirs.flags,
+        Some(base) if base == theirs.flags => ours.flags,
+        base => {
+            conflicts.push(Conflict::Flags { base, ours: ours.flags, theirs: theirs.flags });
+            ours.flags
+        },
+    };
+    let fields = merge_fields(base.map(|x| &x.fields[..]), &ours.fields, &theirs.fields, |tag, occurrence, base, ours, theirs| {
+        conflicts.push(Conflict::Field { tag, occurrence, base, ours, theirs });
+    });
+    (Record { tag: ours.tag, flags, fields }, conflicts)
+}
+
+fn merge_cells(base: Option<CellReferences>, ours: CellReferences, theirs: CellReferences) -> (Record, Vec<Conflict>) {
+    fn keyed(references: Vec<CellReference>) -> Vec<((Option<i32>, usize), CellReference)> {
+        let mut nth = HashMap::new();
+        references.into_iter().map(|reference| {
+            let ref_num = reference.ref_num().copied();
+            let n = nth.entry(ref_num).or_insert(0);
+            *n += 1;
+            ((ref_num, *n - 1), reference)
+        }).collect()
+    }
+
+    let (header, mut conflicts) = merge_header(base.as_ref().map(|x| &x.header), &ours.header, &theirs.header);
+    let references = merge_lists(
+        base.map_or_else(Vec::new, |x| keyed(x.references)), keyed(ours.references), keyed(theirs.references),
+        |&(ref_num, _), base, ours, theirs| {
+            let conflict = match (base, ours, theirs) {
+                (base, Some(ours), Some(theirs)) => {
+                    let fields = merge_fields(
+                        base.as_ref().map(|x| &x.fields[..]), &ours.fields, &theirs.fields,
+                        |tag, occurrence, base, ours, theirs| conflicts.push(Conflict::ReferenceField {
+                            ref_num, tag, occurrence, base, ours, theirs
+                        })
+                    );
+                    return Some(CellReference { fields });
+                },
+                (None, ours, theirs) => return ours.or(theirs),
+                (Some(_), None, None) => return None,
+                (Some(base), Some(ours), None) => {
+                    if base == ours { return None; }
+                    Conflict::ReferenceRemoved { ref_num, side: Side::Theirs, base, modified: ours }
+                },
+                (Some(base), None, Some(theirs)) => {
+                    if base == theirs { return None; }
+                    Conflict::ReferenceRemoved { ref_num, side: Side::Ours, base, modified: theirs }
+                },
+            };
+            let merged = match &conflict {
+                Conflict::ReferenceRemoved { side: Side::Theirs, modified, .. } => Some(modified.clone()),
+                _ => None
+            };
+            conflicts.push(conflict);
+            merged
+        }
+    );
+    (CellReferences { header, references }.into(), conflicts)
+}
+
+pub fn merge_records(base: Option<&Record>, ours: &Record, theirs: &Record) -> (Record, Vec<Conflict>) {
+    if ours.tag == CELL && theirs.tag == CELL && base.is_none_or(|x| x.tag == CELL) {
+        let cell = |record: &Record| CellReferences::try_from(record).unwrap();
+        return merge_cells(base.map(cell), cell(ours), cell(theirs));
+    }
+    merge_header(base, ours, theirs)
+}
+
+pub fn merge_files(base: &[Record], ours: &[Record], theirs: &[Record]) -> (Vec<Record>, Vec<MergeConflict>) {
+    fn keyed(records: &[Record]) -> Vec<((Tag, RecordKey), Record)> {
+        record_keys(records).into_iter().zip(records.iter().cloned()).collect()
+    }
+
+    let mut conflicts = Vec::new();
+    let records = merge_lists(keyed(base), keyed(ours), keyed(theirs), |(tag, key), base, ours, theirs| {
+        let id = key.id();
+        let conflict = match (base, ours, theirs) {
+            (base, Some(ours), Some(theirs)) => {
+                let (merged, record_conflicts) = merge_records(base.as_ref(), &ours, &theirs);
+                conflicts.extend(record_conflicts.into_iter().map(|conflict| MergeConflict {
+                    tag: *tag, id: id.clone(), conflict
+                }));
+                return Some(merged);
+            },
+            (None, ours, theirs) => return ours.or(theirs),
+            (Some(_), None, None) => return None,
+            (Some(base), Some(ours), None) => {
+                if base == ours { return None; }
+                Conflict::Removed { side: Side::Theirs, base, modified: ours }
+            },
+            (Some(base), None, Some(theirs)) => {
+                if base == theirs { return None; }
+                Conflict::Removed { side: Side::Ours, base, modified: theirs }
+            },
+        };
+        let merged = match &conflict {
+            Conflict::Removed { side: Side::Theirs, modified, .. } => Some(modified.clone()),
+            _ => None
+        };
+        conflicts.push(MergeConflict { tag: *tag, id, conflict });
+        merged
+    });
+    (records, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::merge::*;
+    use std::str::FromStr;
+
+    fn misc(id: &str, name: &str, value: i32) -> Record {
+        Record {
+            tag: Tag::from_str("MISC").unwrap(),
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (NAME, Field::StringZ(id.into())),
+                (FNAM, Field::StringZ(name.into())),
+                (Tag::from_str("MCDT").unwrap(), Field::I32(value)),
+            ]
+        }
+    }
+
+    #[test]
+    fn merge_fields() {
+        let base = misc("a", "A", 1);
+        let ours = misc("a", "B", 1);
+        let mut theirs = misc("a", "A", 2);
+        theirs.fields.push((SCRI, Field::StringZ("script".into())));
+        let (merged, conflicts) = merge_records(Some(&base), &ours, &theirs);
+        assert_eq!(conflicts, Vec::new());
+        assert_eq!(merged.fields, vec![
+            (NAME, Field::StringZ("a".into())),
+            (FNAM, Field::StringZ("B".into())),
+            (Tag::from_str("MCDT").unwrap(), Field::I32(2)),
+            (SCRI, Field::StringZ("script".into())),
+        ]);
+        let (merged, conflicts) = merge_records(Some(&base), &ours, &misc("a", "C", 1));
+        assert_eq!(merged, ours);
+        assert_eq!(conflicts, vec![Conflict::Field {
+            tag: FNAM,
+            occurrence: 0,
+            base: Some(Field::StringZ("A".into())),
+            ours: Some(Field::StringZ("B".into())),
+            theirs: Some(Field::StringZ("C".into()))
+        }]);
+    }
+
+    #[test]
+    fn merge_plugins() {
+        let base = vec![misc("a", "A", 1), misc("b", "B", 1), misc("c", "C", 1)];
+        let ours = vec![misc("a", "A", 1), misc("d", "D", 1), misc("c", "C", 2)];
+        let theirs = vec![misc("a", "A", 1), misc("b", "B", 1), misc("e", "E", 1)];
+        let (merged, conflicts) = merge_files(&base, &ours, &theirs);
+        let names = merged.iter().map(|x| x.fields[0].1.clone()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "e", "d", "c"].map(|x| Field::StringZ(x.into())));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, Some(RecordId::Name("c".into())));
+        assert!(matches!(conflicts[0].conflict, Conflict::Removed { side: Side::Theirs, .. }));
+        let base = vec![misc("a", "A", 1), misc("a", "A", 2)];
+        let ours = vec![misc("a", "A", 1), misc("a", "A", 3)];
+        let (merged, conflicts) = merge_files(&base, &ours, &base);
+        assert_eq!(conflicts, Vec::new());
+        assert_eq!(merged, ours);
+    }
+
+    #[test]
+    fn merge_repeated_fields() {
+        let npc = |spells: &[&str]| Record {
+            tag: NPC_,
+            flags: RecordFlags::empty(),
+            fields: [(NAME, "fargoth")].into_iter().chain(spells.iter().map(|&x| (NPCS, x)))
+                .map(|(tag, x)| (tag, Field::StringZ(x.into()))).collect()
+        };
+        let base = npc(&["shield", "fire bite"]);
+        let ours = npc(&["hearth heal", "shield", "fire bite"]);
+        let theirs = npc(&["shield", "frostbite"]);
+        let (merged, conflicts) = merge_records(Some(&base), &ours, &theirs);
+        assert_eq!(conflicts, Vec::new());
+        assert_eq!(merged, npc(&["hearth heal", "shield", "frostbite"]));
+    }
+
+    #[test]
+    fn merge_cell_references() {
+        let reference = |ref_num: i32, id: &str| [
+            (FRMR, Field::I32(ref_num)),
+            (NAME, Field::StringZ(id.into())),
+        ];
+        let cell = |references: &[[(Tag, Field); 2]]| Record {
+            tag: CELL,
+            flags: RecordFlags::empty(),
+            fields: [(NAME, Field::StringZ("Seyda Neen".into()))].into_iter()
+                .chain(references.iter().flat_map(|x| x.iter().cloned())).collect()
+        };
+        let base = cell(&[reference(1, "barrel_01"), reference(2, "crate_01")]);
+        let ours = cell(&[reference(1, "barrel_02"), reference(2, "crate_01"), reference(3, "chest_01")]);
+        let theirs = cell(&[reference(1, "barrel_01"), reference(4, "sack_01")]);
+        let (merged, conflicts) = merge_records(Some(&base), &ours, &theirs);
+        assert_eq!(conflicts, Vec::new());
+        assert_eq!(merged, cell(&[reference(1, "barrel_02"), reference(4, "sack_01"), reference(3, "chest_01")]));
+        let theirs = cell(&[reference(1, "barrel_03"), reference(2, "crate_01")]);
+        let (_, conflicts) = merge_records(Some(&base), &ours, &theirs);
+        assert_eq!(conflicts, vec![Conflict::ReferenceField {
+            ref_num: Some(1),
+            tag: NAME,
+            occurrence: 0,
+            base: Some(Field::StringZ("barrel_01".into())),
+            ours: Some(Field::StringZ("barrel_02".into())),
+            theirs: Some(Field::StringZ("barrel_03".into()))
+        }]);
+    }
+}
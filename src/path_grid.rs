@@ -0,0 +1,251 @@
+use crate::field::*;
+use crate::record::*;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum PathGridError {
+    Zip(io::Error),
+    UnexpectedRecord(Tag),
+    UnexpectedFieldType(Tag),
+    UnexpectedSize { field_tag: Tag, size: usize },
+    AutoGenerated { point: usize, value: u8 },
+    PointsCount { metadata: u16, actual: usize },
+    ConnectionsCount { points: usize, actual: usize },
+    InvalidConnection { point: usize, target: u32 },
+    TooManyPoints(usize),
+    TooManyConnections { point: usize, count: usize },
+}
+
+impl Display for PathGridError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PathGridError::Zip(e) => write!(f, "invalid compressed data: {e}"),
+            PathGridError::UnexpectedRecord(record_tag) => write!(f, "PGRD record expected, but {record_tag} record found"),
+            PathGridError::UnexpectedFieldType(field_tag) => write!(f, "{field_tag} field should have byte list type"),
+            PathGridError::UnexpectedSize { field_tag, size } => write!(f, "invalid {field_tag} field size {size}"),
+            PathGridError::AutoGenerated { point, value } =>
+                write!(f, "invalid auto generated flag {value} of point {point}"),
+            PathGridError::PointsCount { metadata, actual } =>
+                write!(f, "points count mismatch: {metadata} in DATA, {actual} in PGRP"),
+            PathGridError::ConnectionsCount { points, actual } =>
+                write!(f, "connections count mismatch: {points} in PGRP, {actual} in PGRC"),
+            PathGridError::InvalidConnection { point, target } =>
+                write!(f, "point {point} connected to nonexistent point {target}"),
+            PathGridError::TooManyPoints(count) => write!(f, "too many points ({count}), 65535 allowed"),
+            PathGridError::TooManyConnections { point, count } =>
+                write!(f, "too many connections ({count}) of point {point}, 255 allowed"),
+        }
+    }
+}
+
+impl Error for PathGridError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        if let PathGridError::Zip(e) = self { Some(e) } else { None }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PathGridPoint {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub auto_generated: bool,
+    pub padding: u16,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct PathGridGraph {
+    pub points: Vec<PathGridPoint>,
+    pub edges: Vec<(u32, u32)>,
+}
+
+fn path_grid_bytes(field_tag: Tag, field: Option<&Field>, zip_mode: ZipMode, item_size: usize) -> Result<Vec<u8>, PathGridError> {
+    let Some(field) = field else { return Ok(Vec::new()); };
+    let Field::U8List(bytes) = field else { return Err(PathGridError::UnexpectedFieldType(field_tag)); };
+    let bytes = zip_mode.decode(bytes).map_err(PathGridError::Zip)?;
+    if bytes.len() % item_size != 0 {
+        return Err(PathGridError::UnexpectedSize { field_tag, size: bytes.len() });
+    }
+    Ok(bytes.into_owned())
+}
+
+impl PathGridGraph {
+    pub fn from_fields(
+        path_grid: &PathGrid, points: Option<&Field>, connections: Option<&Field>, zip_mode: ZipMode
+    ) -> Result<Self, PathGridError> {
+        let mut connection_counts = Vec::new();
+        let points = path_grid_bytes(PGRP, points, zip_mode, 16)?.chunks(16).enumerate().map(|(point, x)| {
+            let auto_generated = match x[12] {
+                0 => false,
+                1 => true,
+                value => return Err(PathGridError::AutoGenerated { point, value }),
+            };
+            connection_counts.push(x[13] as usize);
+            Ok(PathGridPoint {
+                x: i32::from_le_bytes(x[0 .. 4].try_into().unwrap()),
+                y: i32::from_le_bytes(x[4 .. 8].try_into().unwrap()),
+                z: i32::from_le_bytes(x[8 .. 12].try_into().unwrap()),
+                auto_generated,
+                padding: u16::from_le_bytes([x[14], x[15]]),
+            })
+        }).collect::<Result<Vec<_>, _>>()?;
+        if points.len() != path_grid.points as usize {
+            return Err(PathGridError::PointsCount { metadata: path_grid.points, actual: points.len() });
+        }
+        let targets = path_grid_bytes(PGRC, connections, zip_mode, 4)?.chunks(4)
+            .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let connections_count = connection_counts.iter().sum::<usize>();
+        if connections_count != targets.len() {
+            return Err(PathGridError::ConnectionsCount { points: connections_count, actual: targets.len() });
+        }
+        let mut targets = targets.into_iter();
+        let mut edges = Vec::with_capacity(connections_count);
+        for (point, &connection_count) in connection_counts.iter().enumerate() {
+            for target in targets.by_ref().take(connection_count) {
+                if target as usize >= points.len() {
+                    return Err(PathGridError::InvalidConnection { point, target });
+                }
+                edges.push((point as u32, target));
+            }
+        }
+        Ok(PathGridGraph { points, edges })
+    }
+
+    pub fn to_fields(&self, zip_mode: ZipMode) -> Result<(Field, Field), PathGridError> {
+        let adjacency = self.adjacency()?;
+        let mut points = Vec::with_capacity(self.points.len() * 16);
+        let mut connections = Vec::with_capacity(self.edges.len() * 4);
+        for (point, (x, targets)) in self.points.iter().zip(adjacency.iter()).enumerate() {
+            let Ok(connection_count) = u8::try_from(targets.len()) else {
+                return Err(PathGridError::TooManyConnections { point, count: targets.len() });
+            };
+            points.extend_from_slice(&x.x.to_le_bytes());
+            points.extend_from_slice(&x.y.to_le_bytes());
+            points.extend_from_slice(&x.z.to_le_bytes());
+            points.push(x.auto_generated as u8);
+            points.push(connection_count);
+            points.extend_from_slice(&x.padding.to_le_bytes());
+            connections.extend(targets.iter().flat_map(|x| x.to_le_bytes()));
+        }
+        Ok((
+            Field::U8List(zip_mode.encode(&points).into_owned()),
+            Field::U8List(zip_mode.encode(&connections).into_owned())
+        ))
+    }
+
+    pub fn adjacency(&self) -> Result<Vec<Vec<u32>>, PathGridError> {
+        let mut adjacency = vec![Vec::new(); self.points.len()];
+        for &(point, target) in &self.edges {
+            let Some(targets) = adjacency.get_mut(point as usize).filter(|_| (target as usize) < self.points.len()) else {
+                return Err(PathGridError::InvalidConnection { point: point as usize, target });
+            };
+            targets.push(target);
+        }
+        Ok(adjacency)
+    }
+
+    pub fn neighbors(&self, point: u32) -> impl Iterator<Item=u32> + '_ {
+        self.edges.iter().filter(move |x| x.0 == point).map(|x| x.1)
+    }
+
+    pub fn connection_count(&self, point: u32) -> usize { self.neighbors(point).count() }
+
+    pub fn is_connected(&self, point: u32, target: u32) -> bool {
+        self.edges.contains(&(point, target))
+    }
+
+    pub fn connect(&mut self, a: u32, b: u32) {
+        if !self.is_connected(a, b) { self.edges.push((a, b)); }
+        if !self.is_connected(b, a) { self.edges.push((b, a)); }
+    }
+
+    pub fn disconnect(&mut self, a: u32, b: u32) {
+        self.edges.retain(|&x| x != (a, b) && x != (b, a));
+    }
+
+    pub fn remove_point(&mut self, point: u32) -> PathGridPoint {
+        let removed = self.points.remove(point as usize);
+        self.edges.retain(|x| x.0 != point && x.1 != point);
+        for edge in &mut self.edges {
+            if edge.0 > point { edge.0 -= 1; }
+            if edge.1 > point { edge.1 -= 1; }
+        }
+        removed
+    }
+}
+
+impl Record {
+    pub fn path_grid_graph(&self, zip_mode: ZipMode) -> Option<Result<PathGridGraph, PathGridError>> {
+        if self.tag != PGRD { return None; }
+        let path_grid = self.fields.iter().find_map(|x| if let (DATA, Field::PathGrid(v)) = x { Some(v) } else { None })?;
+        let field = |tag: Tag| self.fields.iter().find(|x| x.0 == tag).map(|x| &x.1);
+        Some(PathGridGraph::from_fields(path_grid, field(PGRP), field(PGRC), zip_mode))
+    }
+
+    pub fn set_path_grid_graph(&mut self, graph: &PathGridGraph, zip_mode: ZipMode) -> Result<(), PathGridError> {
+        if self.tag != PGRD { return Err(PathGridError::UnexpectedRecord(self.tag)); }
+        let Ok(points_count) = u16::try_from(graph.points.len()) else {
+            return Err(PathGridError::TooManyPoints(graph.points.len()));
+        };
+        let (points, connections) = graph.to_fields(zip_mode)?;
+        for (tag, field) in &mut self.fields {
+            if let (DATA, Field::PathGrid(v)) = (*tag, field) {
+                v.points = points_count;
+            }
+        }
+        self.fields.retain(|x| x.0 != PGRP && x.0 != PGRC);
+        let index = self.fields.iter().position(|x| x.0 == DATA).map_or(self.fields.len(), |x| x + 1);
+        let index = self.fields.iter().skip(index).position(|x| x.0 != NAME).map_or(self.fields.len(), |x| x + index);
+        if !graph.points.is_empty() {
+            self.fields.insert(index, (PGRP, points));
+            if !graph.edges.is_empty() {
+                self.fields.insert(index + 1, (PGRC, connections));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn point(x: i32) -> PathGridPoint {
+        PathGridPoint { x, y: -x, z: 0, auto_generated: x % 2 == 0, padding: 0 }
+    }
+
+    #[test]
+    fn path_grid_round_trip() {
+        let graph = PathGridGraph {
+            points: vec![point(0), point(1), point(2)],
+            edges: vec![(0, 1), (0, 2), (1, 0), (2, 0)],
+        };
+        let mut record = Record {
+            tag: PGRD,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (DATA, Field::PathGrid(PathGrid { grid: Grid { x: 1, y: 2 }, flags: 1024, points: 0 })),
+                (NAME, Field::StringZ("Balmora".into())),
+            ]
+        };
+        record.set_path_grid_graph(&graph, ZipMode::Compressed).unwrap();
+        assert_eq!(record.fields.iter().map(|x| x.0).collect::<Vec<_>>(), vec![DATA, NAME, PGRP, PGRC]);
+        let mut decoded = record.path_grid_graph(ZipMode::Compressed).unwrap().unwrap();
+        assert_eq!(decoded, graph);
+        assert_eq!(decoded.neighbors(0).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(decoded.connection_count(0), 2);
+        decoded.remove_point(1);
+        assert_eq!(decoded.edges, vec![(0, 1), (1, 0)]);
+        let Field::PathGrid(path_grid) = &record.fields[0].1 else { panic!() };
+        assert!(matches!(
+            PathGridGraph::from_fields(path_grid, Some(&record.fields[2].1), None, ZipMode::Compressed),
+            Err(PathGridError::ConnectionsCount { points: 4, actual: 0 })
+        ));
+        let mut cell = Record { tag: CELL, flags: RecordFlags::empty(), fields: Vec::new() };
+        assert!(matches!(cell.set_path_grid_graph(&graph, ZipMode::Compressed), Err(PathGridError::UnexpectedRecord(CELL))));
+        assert!(cell.fields.is_empty());
+    }
+}
@@ -4,8 +4,6 @@ use crate::strings::*;
 use crate::field::*;
 use crate::record::*;
 use either::{Right, Left, Either};
-use flate2::write::ZlibEncoder;
-use flate2::Compression;
 use nom::IResult;
 use nom::combinator::{map, flat_map, cut};
 use nom::sequence::{pair, tuple, preceded};
@@ -18,9 +16,12 @@ use std::convert::TryInto;
 use std::error::Error;
 use std::fmt::{self, Display, Debug, Formatter};
 use std::hash::{Hash, Hasher};
-use std::io::{self, Read, Write};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::mem::{replace, transmute};
 use std::sync::LazyLock;
+use std::thread;
+#[cfg(feature="async")]
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 #[derive(Eq, Clone, Copy)]
 struct Void(!);
@@ -184,10 +185,8 @@ fn script_data_field<E>(code_page: CodePage) -> impl FnMut(&[u8]) -> IResult<&[u
     move |input| Ok((&input[input.len() .. ], ScriptData::from_bytes(code_page, input)))
 }
 
-fn u8_list_zip_field<E>(input: &[u8]) -> IResult<&[u8], Vec<u8>, E> {
-    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(5));
-    encoder.write_all(input).unwrap();
-    Ok((&input[input.len() .. ], encoder.finish().unwrap()))
+fn u8_list_zip_field<'a, E>(zip_mode: ZipMode) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<u8>, E> {
+    move |input| Ok((&input[input.len() .. ], zip_mode.encode(input).into_owned()))
 }
 
 fn trim_end_nulls(bytes: &[u8]) -> &[u8] {
@@ -1635,13 +1634,14 @@ fn field_body<'a>(
     field_tag: Tag,
     field_size: u32,
     omwsave: bool,
+    zip_mode: ZipMode,
 ) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Field, FieldBodyError> {
     move |input| {
         let field_type = FieldType::from_tags(record_tag, prev_tag, field_tag, omwsave);
         match field_type {
             FieldType::U8List => map(u8_list_field, Field::U8List)(input),
             FieldType::ScriptData => map(script_data_field(code_page), Field::ScriptData)(input),
-            FieldType::U8ListZip => map(u8_list_zip_field, Field::U8List)(input),
+            FieldType::U8ListZip => map(u8_list_zip_field(zip_mode), Field::U8List)(input),
             FieldType::Multiline(newline) => map(multiline_field(code_page, newline), Field::StringList)(input),
             FieldType::Item => map(item_field(code_page), Field::Item)(input),
             FieldType::CurrentTime => map(current_time_field, Field::CurrentTime)(input),
@@ -1775,13 +1775,14 @@ fn field<'a>(
     record_tag: Tag,
     prev_tag: Tag,
     omwsave: bool,
+    zip_mode: ZipMode,
 ) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], (Tag, Field), FieldError> {
     map_res(
         and_then(
             field_bytes,
             move |(field_tag, field_size, field_bytes), _| {
                 let (remaining_field_bytes, field_body) = map_err(
-                    field_body(code_page, mode, record_tag, prev_tag, field_tag, field_size, omwsave),
+                    field_body(code_page, mode, record_tag, prev_tag, field_tag, field_size, omwsave, zip_mode),
                     move |e, _| match e {
                         FieldBodyError::UnexpectedEndOfField(n) => FieldError::FieldSizeMismatch(field_tag, n, field_size),
                         FieldBodyError::UnknownValue(v, o) => FieldError::UnknownValue(field_tag, v, o),
@@ -2178,6 +2179,7 @@ fn record_body<'a>(
     mode: RecordReadMode,
     record_tag: Tag,
     omwsave: bool,
+    zip_mode: ZipMode,
 ) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<(Tag, Field)>, RecordBodyError<'a>> {
     sliding_many0(
         move |prev_tag: Tag| preceded(
@@ -2189,51 +2191,55 @@ fn record_body<'a>(
                     Ok((input, ()))
                 }
             },
-            cut(map_err(field(code_page, mode, record_tag, prev_tag, omwsave), RecordBodyError))
+            cut(map_err(field(code_page, mode, record_tag, prev_tag, omwsave, zip_mode), RecordBodyError))
         ),
         |s: &(Tag, Field)| s.0,
         META
     )
 }
 
+fn record_body_error(record_offset: u64, record_tag: Tag, record_size: u32, input: &[u8], e: RecordBodyError) -> RecordError {
+    match e {
+        RecordBodyError(FieldError::UnexpectedEndOfRecord(n), field) =>
+            RecordError::RecordSizeMismatch(RecordSizeMismatch {
+                record_offset, record_tag,
+                expected_size: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32 + n,
+                actual_size: record_size
+            }),
+        RecordBodyError(FieldError::FieldSizeMismatch(field_tag, expected_size, actual_size), field) =>
+            RecordError::FieldSizeMismatch(FieldSizeMismatch {
+                record_offset, record_tag, field_tag, expected_size, actual_size,
+                field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32
+            }),
+        RecordBodyError(FieldError::UnknownValue(field_tag, value, value_offset), field) =>
+            RecordError::UnknownValue(UnknownValue {
+                record_offset, value,
+                field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32,
+                record_tag, field_tag, value_offset
+            }),
+        RecordBodyError(FieldError::InvalidValue(field_tag, value, value_offset), field) =>
+            RecordError::InvalidValue(InvalidValue {
+                record_offset, value,
+                field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32,
+                record_tag, field_tag, value_offset
+            }),
+        RecordBodyError(FieldError::UnexpectedFieldSize(field_tag, field_size), field) =>
+            RecordError::UnexpectedFieldSize(UnexpectedFieldSize {
+                record_offset, field_size,
+                field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32,
+                record_tag, field_tag
+            }),
+    }
+}
+
 fn read_record_body(record_offset: u64, code_page: CodePage, mode: RecordReadMode,
                     record_tag: Tag, record_size: u32, record_flags: RecordFlags, omwsave: bool,
-                    input: &[u8])
+                    zip_mode: ZipMode, input: &[u8])
     -> Result<Record, RecordError> {
     
     let (remaining_record_bytes, record_body) = map_err(
-        record_body(code_page, mode, record_tag, omwsave),
-        move |e, input| match e {
-            RecordBodyError(FieldError::UnexpectedEndOfRecord(n), field) =>
-                RecordError::RecordSizeMismatch(RecordSizeMismatch {
-                    record_offset, record_tag,
-                    expected_size: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32 + n,
-                    actual_size: record_size
-                }),
-            RecordBodyError(FieldError::FieldSizeMismatch(field_tag, expected_size, actual_size), field) =>
-                RecordError::FieldSizeMismatch(FieldSizeMismatch {
-                    record_offset, record_tag, field_tag, expected_size, actual_size,
-                    field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32
-                }),
-            RecordBodyError(FieldError::UnknownValue(field_tag, value, value_offset), field) =>
-                RecordError::UnknownValue(UnknownValue {
-                    record_offset, value,
-                    field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32,
-                    record_tag, field_tag, value_offset
-                }),
-            RecordBodyError(FieldError::InvalidValue(field_tag, value, value_offset), field) =>
-                RecordError::InvalidValue(InvalidValue {
-                    record_offset, value,
-                    field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32,
-                    record_tag, field_tag, value_offset
-                }),
-            RecordBodyError(FieldError::UnexpectedFieldSize(field_tag, field_size), field) =>
-                RecordError::UnexpectedFieldSize(UnexpectedFieldSize {
-                    record_offset, field_size,
-                    field_offset: unsafe { field.as_ptr().offset_from(input.as_ptr()) } as u32,
-                    record_tag, field_tag
-                }),
-        }
+        record_body(code_page, mode, record_tag, omwsave, zip_mode),
+        move |e, input| record_body_error(record_offset, record_tag, record_size, input, e)
     )(input).map_err(|x| x.unwrap())?;
     if !remaining_record_bytes.is_empty() {
         return Err(RecordError::RecordSizeMismatch(RecordSizeMismatch {
@@ -2319,16 +2325,23 @@ pub enum RecordReadMode {
 
 pub struct RecordReader {
     buf: Vec<u8>,
+    zip_mode: ZipMode,
 }
 
 #[allow(clippy::new_without_default)]
 impl RecordReader {
     pub fn new() -> Self {
         RecordReader {
-            buf: Vec::with_capacity(16)
+            buf: Vec::with_capacity(16),
+            zip_mode: ZipMode::Compressed
         }
     }
 
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.zip_mode = zip_mode;
+        self
+    }
+
     fn read_chunk(&mut self, input: &mut (impl Read + ?Sized)) -> Result<usize, ReadRecordError> {
         read_and_ignore_interrupts(input, &mut self.buf[..])
             .map_err(|io_error| ReadRecordError {
@@ -2337,48 +2350,42 @@ impl RecordReader {
             })
     }
 
+    fn fill_error(&mut self, from: usize, io_error: io::Error) -> ReadRecordError {
+        let mut bytes = replace(&mut self.buf, Vec::with_capacity(16));
+        bytes.truncate(from);
+        ReadRecordError { source: Right(io_error), bytes }
+    }
+
     fn fill_buf(&mut self, mut from: usize, input: &mut (impl Read + ?Sized))
         -> Result<(), ReadRecordError> {
 
         while from < self.buf.len() {
-            let read = read_and_ignore_interrupts(input, &mut self.buf[from..])
-                .map_err(|io_error| ReadRecordError {
-                    source: Right(io_error),
-                    bytes: {
-                        let mut bytes = replace(&mut self.buf, Vec::with_capacity(16));
-                        bytes.truncate(from);
-                        bytes
-                    }
-                })?;
+            let read = match read_and_ignore_interrupts(input, &mut self.buf[from..]) {
+                Ok(read) => read,
+                Err(io_error) => return Err(self.fill_error(from, io_error)),
+            };
             if read == 0 {
-                return Err(ReadRecordError {
-                    source: Right(io::Error::from(io::ErrorKind::UnexpectedEof)),
-                    bytes: {
-                        let mut bytes = replace(&mut self.buf, Vec::with_capacity(16));
-                        bytes.truncate(from);
-                        bytes
-                    }
-                });
+                return Err(self.fill_error(from, io::Error::from(io::ErrorKind::UnexpectedEof)));
             }
             from += read;
         }
         Ok(())
     }
 
-    pub fn read<Input: Read + ?Sized>(&mut self, code_page: CodePage, mode: RecordReadMode, omwsave: bool, offset: u64, input: &mut Input)
-        -> Result<Option<(Record, u32)>, ReadRecordError> {
-
-        self.buf.resize(16, 0);
-        let read = self.read_chunk(input)?;
-        if read == 0 { return Ok(None); }
-        self.fill_buf(read, input)?;
-        let (record_tag, record_size, record_flags) =
+    fn read_head(&mut self) -> Result<(), ReadRecordError> {
+        let (_, record_size, _) =
             read_record_head(&self.buf[..]).map_err(|record_error| ReadRecordError {
                 source: Left(record_error),
                 bytes: replace(&mut self.buf, Vec::with_capacity(16))
             })?;
         self.buf.resize(16 + record_size as usize, 0);
-        self.fill_buf(16, input)?;
+        Ok(())
+    }
+
+    fn read_body(&mut self, code_page: CodePage, mode: RecordReadMode, omwsave: bool, offset: u64)
+        -> Result<(Record, u32), ReadRecordError> {
+
+        let (record_tag, record_size, record_flags) = read_record_head(&self.buf[.. 16]).unwrap();
         let record_flags = RecordFlags::from_bits(record_flags)
             .ok_or_else(|| ReadRecordError {
                 source: Left(RecordError::UnknownRecordFlags(UnknownRecordFlags {
@@ -2389,13 +2396,67 @@ impl RecordReader {
                 bytes: replace(&mut self.buf, Vec::with_capacity(16))
             })?;
         let record = read_record_body(
-            offset, code_page, mode, record_tag, record_size, record_flags, omwsave,
+            offset, code_page, mode, record_tag, record_size, record_flags, omwsave, self.zip_mode,
             &self.buf[16..]).map_err(|record_error| ReadRecordError {
                 source: Left(record_error),
                 bytes: replace(&mut self.buf, Vec::with_capacity(16))
             }
         )?;
-        Ok(Some((record, 16 + record_size)))
+        Ok((record, 16 + record_size))
+    }
+
+    pub fn read<Input: Read + ?Sized>(&mut self, code_page: CodePage, mode: RecordReadMode, omwsave: bool, offset: u64, input: &mut Input)
+        -> Result<Option<(Record, u32)>, ReadRecordError> {
+
+        self.buf.resize(16, 0);
+        let read = self.read_chunk(input)?;
+        if read == 0 { return Ok(None); }
+        self.fill_buf(read, input)?;
+        self.read_head()?;
+        self.fill_buf(16, input)?;
+        self.read_body(code_page, mode, omwsave, offset).map(Some)
+    }
+
+    #[cfg(feature="async")]
+    async fn read_chunk_async(&mut self, input: &mut (impl AsyncRead + Unpin + ?Sized)) -> Result<usize, ReadRecordError> {
+        loop {
+            match input.read(&mut self.buf[..]).await {
+                Ok(read) => return Ok(read),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => { },
+                Err(io_error) => return Err(ReadRecordError { source: Right(io_error), bytes: Vec::new() }),
+            }
+        }
+    }
+
+    #[cfg(feature="async")]
+    async fn fill_buf_async(&mut self, mut from: usize, input: &mut (impl AsyncRead + Unpin + ?Sized))
+        -> Result<(), ReadRecordError> {
+
+        while from < self.buf.len() {
+            let read = match input.read(&mut self.buf[from..]).await {
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(io_error) => return Err(self.fill_error(from, io_error)),
+            };
+            if read == 0 {
+                return Err(self.fill_error(from, io::Error::from(io::ErrorKind::UnexpectedEof)));
+            }
+            from += read;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature="async")]
+    pub async fn read_async<Input: AsyncRead + Unpin + ?Sized>(
+        &mut self, code_page: CodePage, mode: RecordReadMode, omwsave: bool, offset: u64, input: &mut Input
+    ) -> Result<Option<(Record, u32)>, ReadRecordError> {
+        self.buf.resize(16, 0);
+        let read = self.read_chunk_async(input).await?;
+        if read == 0 { return Ok(None); }
+        self.fill_buf_async(read, input).await?;
+        self.read_head()?;
+        self.fill_buf_async(16, input).await?;
+        self.read_body(code_page, mode, omwsave, offset).map(Some)
     }
 }
 
@@ -2419,6 +2480,15 @@ impl<'a, Input: Read + ?Sized> Records<'a, Input> {
             reader: RecordReader::new()
         }
     }
+
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.reader = self.reader.with_zip_mode(zip_mode);
+        self
+    }
+
+    pub fn offset(&self) -> u64 { self.offset }
+
+    pub fn spans(self) -> RecordSpans<'a, Input> { RecordSpans(self) }
 }
 
 impl<'a, Input: Read + ?Sized> Iterator for Records<'a, Input> {
@@ -2439,6 +2509,535 @@ impl<'a, Input: Read + ?Sized> Iterator for Records<'a, Input> {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RecordSpan {
+    pub offset: u64,
+    pub size: u32,
+    pub record: Record,
+}
+
+pub struct RecordSpans<'a, Input: Read + ?Sized>(Records<'a, Input>);
+
+impl<'a, Input: Read + ?Sized> RecordSpans<'a, Input> {
+    pub fn offset(&self) -> u64 { self.0.offset }
+}
+
+impl<'a, Input: Read + ?Sized> Iterator for RecordSpans<'a, Input> {
+    type Item = Result<RecordSpan, ReadRecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.0.offset;
+        let record = self.0.next()?;
+        Some(record.map(|record| RecordSpan { offset, size: (self.0.offset - offset) as u32, record }))
+    }
+}
+
+#[cfg(feature="async")]
+pub struct AsyncRecords<'a, Input: AsyncRead + Unpin + ?Sized> {
+    code_page: CodePage,
+    mode: RecordReadMode,
+    input: &'a mut Input,
+    omwsave: bool,
+    offset: u64,
+    reader: RecordReader,
+}
+
+#[cfg(feature="async")]
+impl<'a, Input: AsyncRead + Unpin + ?Sized> AsyncRecords<'a, Input> {
+    pub fn new(code_page: CodePage, mode: RecordReadMode, omwsave: bool, offset: u64, input: &'a mut Input) -> Self {
+        AsyncRecords {
+            code_page,
+            mode,
+            input,
+            omwsave,
+            offset,
+            reader: RecordReader::new()
+        }
+    }
+
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.reader = self.reader.with_zip_mode(zip_mode);
+        self
+    }
+
+    pub async fn next_record(&mut self) -> Option<Result<Record, ReadRecordError>> {
+        match self.reader.read_async(self.code_page, self.mode, self.omwsave, self.offset, self.input).await {
+            Ok(None) => None,
+            Err(e) => {
+                self.offset += e.as_bytes().len() as u64;
+                Some(Err(e))
+            },
+            Ok(Some((record, read))) => {
+                self.offset += read as u64;
+                Some(Ok(record))
+            }
+        }
+    }
+}
+
+fn is_known_tag(dword: u32) -> bool {
+    TAGS.binary_search(&Tag::from(dword)).is_ok()
+}
+
+const RECORD_TAGS: &[Tag] = &[
+    TES3, GMST, GLOB, CLAS, FACT, RACE, SOUN, SKIL, MGEF, SCPT, REGN, BSGN, LTEX, STAT, DOOR, MISC, WEAP, CONT, SPEL,
+    CREA, BODY, LIGH, ENCH, NPC_, ARMO, CLOT, REPA, ACTI, APPA, LOCK, PROB, INGR, BOOK, ALCH, LEVI, LEVC, CELL, LAND,
+    PGRD, SNDG, DIAL, INFO, SSCR,
+    GAME, REFR, SPLM, PCDT, JOUR, QUES, KLST, FMAP, STLN, NPCC, CREC, CNTC, GSCR,
+    SAVE, GMAP, PLAY, CSTA, ACTC, PROJ, MPRJ, DCOU, MARK, DYNA, ASPL, WTHR, ENAB, CAM_,
+];
+
+fn is_record_tag(dword: u32) -> bool {
+    RECORD_TAGS.contains(&Tag::from(dword))
+}
+
+const MAX_RECOVERED_RECORD_SIZE: u32 = 16 * 1024 * 1024;
+
+fn plausible_record_size(head: &[u8]) -> Option<u32> {
+    let record_tag = u32::from_le_bytes(head[0 .. 4].try_into().unwrap());
+    let record_size = u32::from_le_bytes(head[4 .. 8].try_into().unwrap());
+    let record_flags = u64::from_le_bytes(head[8 .. 16].try_into().unwrap());
+    if !is_record_tag(record_tag) || RecordFlags::from_bits(record_flags).is_none() { return None; }
+    if record_size > MAX_RECOVERED_RECORD_SIZE { return None; }
+    Some(record_size)
+}
+
+fn is_plausible_record_body(mut body: &[u8]) -> bool {
+    while !body.is_empty() {
+        if body.len() < 8 { return false; }
+        let field_tag = u32::from_le_bytes(body[0 .. 4].try_into().unwrap());
+        let field_size = u32::from_le_bytes(body[4 .. 8].try_into().unwrap());
+        if !is_known_tag(field_tag) || field_size as usize > body.len() - 8 { return false; }
+        body = &body[8 + field_size as usize ..];
+    }
+    true
+}
+
+#[derive(Debug)]
+pub struct SkippedBytes {
+    pub error: ReadRecordError,
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl Display for SkippedBytes {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}, skipped {} bytes started at {:X}h", self.error, self.size, self.offset)
+    }
+}
+
+impl Error for SkippedBytes {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { Some(&self.error) }
+}
+
+pub struct RecoveringRecords<'a, Input: Read + ?Sized> {
+    code_page: CodePage,
+    mode: RecordReadMode,
+    input: &'a mut Input,
+    omwsave: bool,
+    offset: u64,
+    reader: RecordReader,
+    pending: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl<'a, Input: Read + ?Sized> RecoveringRecords<'a, Input> {
+    pub fn new(code_page: CodePage, mode: RecordReadMode, omwsave: bool, offset: u64, input: &'a mut Input) -> Self {
+        RecoveringRecords {
+            code_page,
+            mode,
+            input,
+            omwsave,
+            offset,
+            reader: RecordReader::new(),
+            pending: Vec::new(),
+            error: None
+        }
+    }
+
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.reader = self.reader.with_zip_mode(zip_mode);
+        self
+    }
+
+    fn fill_pending(&mut self, len: usize, eof: &mut bool) {
+        let mut chunk = [0; 4096];
+        while !*eof && self.pending.len() < len {
+            match read_and_ignore_interrupts(self.input, &mut chunk) {
+                Ok(0) => *eof = true,
+                Ok(read) => self.pending.extend_from_slice(&chunk[.. read]),
+                Err(e) => {
+                    self.error = Some(e);
+                    *eof = true;
+                },
+            }
+        }
+    }
+
+    fn resync(&mut self) -> usize {
+        let mut pos = 0;
+        let mut eof = false;
+        loop {
+            self.fill_pending(pos + 16, &mut eof);
+            if self.pending.len() < pos + 16 {
+                pos = self.pending.len();
+                break;
+            }
+            if let Some(record_size) = plausible_record_size(&self.pending[pos .. pos + 16]) {
+                let end = pos + 16 + record_size as usize;
+                self.fill_pending(end, &mut eof);
+                if self.pending.len() >= end && is_plausible_record_body(&self.pending[pos + 16 .. end]) { break; }
+            }
+            pos += 1;
+        }
+        self.pending.drain(.. pos);
+        pos
+    }
+}
+
+impl<'a, Input: Read + ?Sized> Iterator for RecoveringRecords<'a, Input> {
+    type Item = Result<Record, SkippedBytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(SkippedBytes { error: io_error(error), offset: self.offset, size: 0 }));
+        }
+        let mut input = Read::chain(&self.pending[..], &mut *self.input);
+        let res = self.reader.read(self.code_page, self.mode, self.omwsave, self.offset, &mut input);
+        let consumed = self.pending.len() - input.into_inner().0.len();
+        self.pending.drain(.. consumed);
+        match res {
+            Ok(None) => None,
+            Ok(Some((record, read))) => {
+                self.offset += read as u64;
+                Some(Ok(record))
+            },
+            Err(error) => {
+                let offset = self.offset;
+                let resync = match error.source() {
+                    Left(RecordError::RecordSizeMismatch(_)) => true,
+                    Left(RecordError::FieldSizeMismatch(_)) => true,
+                    Left(RecordError::UnknownRecordFlags(_)) => true,
+                    Right(e) => e.kind() == io::ErrorKind::UnexpectedEof,
+                    Left(_) => false,
+                };
+                let size = if resync && !error.as_bytes().is_empty() {
+                    let mut pending = error.as_bytes()[1 ..].to_vec();
+                    pending.append(&mut self.pending);
+                    self.pending = pending;
+                    1 + self.resync() as u64
+                } else {
+                    error.as_bytes().len() as u64
+                };
+                self.offset += size;
+                Some(Err(SkippedBytes { error, offset, size }))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IndexedRecord {
+    pub tag: Tag,
+    pub flags: RecordFlags,
+    pub offset: u64,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RecordIndex {
+    records: Vec<IndexedRecord>,
+}
+
+fn io_error(source: io::Error) -> ReadRecordError {
+    ReadRecordError { source: Right(source), bytes: Vec::new() }
+}
+
+impl RecordIndex {
+    pub fn new<Input: Read + Seek + ?Sized>(input: &mut Input) -> Result<Self, ReadRecordError> {
+        let mut offset = input.stream_position().map_err(io_error)?;
+        let end = input.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let mut records = Vec::new();
+        while offset < end {
+            input.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+            let mut head = [0; 16];
+            input.read_exact(&mut head).map_err(io_error)?;
+            let (tag, size, flags) = read_record_head(&head[..]).map_err(|record_error| ReadRecordError {
+                source: Left(record_error),
+                bytes: head.to_vec()
+            })?;
+            let flags = RecordFlags::from_bits(flags).ok_or_else(|| ReadRecordError {
+                source: Left(RecordError::UnknownRecordFlags(UnknownRecordFlags {
+                    record_offset: offset,
+                    record_tag: tag,
+                    value: flags
+                })),
+                bytes: head.to_vec()
+            })?;
+            if end - offset < 16 + size as u64 {
+                return Err(ReadRecordError {
+                    source: Right(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                    bytes: head.to_vec()
+                });
+            }
+            records.push(IndexedRecord { tag, flags, offset, size });
+            offset += 16 + size as u64;
+        }
+        Ok(RecordIndex { records })
+    }
+
+    pub fn records(&self) -> &[IndexedRecord] { &self.records }
+
+    pub fn read<Input: Read + Seek + ?Sized>(
+        &self,
+        index: usize,
+        code_page: CodePage,
+        mode: RecordReadMode,
+        omwsave: bool,
+        zip_mode: ZipMode,
+        input: &mut Input
+    ) -> Result<Record, ReadRecordError> {
+        let Some(record) = self.records.get(index) else {
+            return Err(io_error(io::Error::new(io::ErrorKind::InvalidInput, format!("record index {index} out of range"))));
+        };
+        let offset = record.offset;
+        input.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        let record = RecordReader::new().with_zip_mode(zip_mode).read(code_page, mode, omwsave, offset, input)?;
+        record.map(|x| x.0).ok_or_else(|| io_error(io::Error::from(io::ErrorKind::UnexpectedEof)))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldRef<'a> {
+    record_offset: u64,
+    record_tag: Tag,
+    record: &'a [u8],
+    omwsave: bool,
+    zip_mode: ZipMode,
+    prev_tag: Tag,
+    tag: Tag,
+    offset: u32,
+    size: u32,
+}
+
+impl<'a> FieldRef<'a> {
+    pub fn tag(&self) -> Tag { self.tag }
+
+    pub fn offset(&self) -> u32 { self.offset }
+
+    pub fn size(&self) -> u32 { self.size }
+
+    pub fn bytes(&self) -> &'a [u8] {
+        &self.record[self.offset as usize + 8 .. self.offset as usize + 8 + self.size as usize]
+    }
+
+    pub fn decode(&self, code_page: CodePage, mode: RecordReadMode) -> Result<Field, RecordError> {
+        let input = &self.record[self.offset as usize ..];
+        map_err(field(code_page, mode, self.record_tag, self.prev_tag, self.omwsave, self.zip_mode), RecordBodyError)(input)
+            .map(|(_, (_, field))| field)
+            .map_err(|e| record_body_error(
+                self.record_offset, self.record_tag, self.record.len() as u32, self.record, e.unwrap()
+            ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldRefs<'a> {
+    record: RecordRef<'a>,
+    offset: usize,
+    prev_tag: Tag,
+}
+
+impl<'a> Iterator for FieldRefs<'a> {
+    type Item = Result<FieldRef<'a>, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.record.bytes();
+        if self.offset >= record.len() { return None; }
+        match field_bytes(&record[self.offset ..]) {
+            Ok((_, (tag, size, _))) => {
+                let field = FieldRef {
+                    record_offset: self.record.offset,
+                    record_tag: self.record.tag,
+                    record,
+                    omwsave: self.record.omwsave,
+                    zip_mode: self.record.zip_mode,
+                    prev_tag: self.prev_tag,
+                    tag,
+                    offset: self.offset as u32,
+                    size
+                };
+                self.offset += 8 + size as usize;
+                self.prev_tag = tag;
+                Some(Ok(field))
+            },
+            Err(e) => {
+                let error = RecordBodyError(e.unwrap(), &record[self.offset ..]);
+                self.offset = record.len();
+                Some(Err(record_body_error(self.record.offset, self.record.tag, record.len() as u32, record, error)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    offset: u64,
+    tag: Tag,
+    flags: RecordFlags,
+    record: &'a [u8],
+    omwsave: bool,
+    zip_mode: ZipMode,
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn tag(&self) -> Tag { self.tag }
+
+    pub fn flags(&self) -> RecordFlags { self.flags }
+
+    pub fn offset(&self) -> u64 { self.offset }
+
+    pub fn size(&self) -> u32 { self.bytes().len() as u32 }
+
+    pub fn bytes(&self) -> &'a [u8] { &self.record[16 ..] }
+
+    pub fn fields(&self) -> FieldRefs<'a> {
+        FieldRefs { record: *self, offset: 0, prev_tag: META }
+    }
+
+    pub fn field(&self, tag: Tag) -> Option<FieldRef<'a>> {
+        self.fields().map_while(Result::ok).find(|x| x.tag == tag)
+    }
+
+    pub fn decode(&self, code_page: CodePage, mode: RecordReadMode) -> Result<Record, RecordError> {
+        read_record_body(self.offset, code_page, mode, self.tag, self.size(), self.flags, self.omwsave, self.zip_mode, self.bytes())
+    }
+
+    pub fn decode_all(&self, code_page: CodePage, mode: RecordReadMode) -> (Record, Vec<RecordError>) {
+        let mut fields = Vec::new();
+        let mut errors = Vec::new();
+        for field in self.fields() {
+            match field {
+                Ok(field) => match field.decode(code_page, mode) {
+                    Ok(decoded) => fields.push((field.tag, decoded)),
+                    Err(e) => {
+                        errors.push(e);
+                        fields.push((field.tag, Field::U8List(field.bytes().to_vec())));
+                    },
+                },
+                Err(e) => errors.push(e),
+            }
+        }
+        (Record { tag: self.tag, flags: self.flags, fields }, errors)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordRefs<'a> {
+    bytes: &'a [u8],
+    omwsave: bool,
+    zip_mode: ZipMode,
+    offset: u64,
+}
+
+impl<'a> RecordRefs<'a> {
+    pub fn new(omwsave: bool, offset: u64, bytes: &'a [u8]) -> Self {
+        RecordRefs { bytes, omwsave, zip_mode: ZipMode::Compressed, offset }
+    }
+
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.zip_mode = zip_mode;
+        self
+    }
+
+    pub fn offset(&self) -> u64 { self.offset }
+}
+
+impl<'a> Iterator for RecordRefs<'a> {
+    type Item = Result<RecordRef<'a>, ReadRecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() { return None; }
+        let record_size = if self.bytes.len() < 16 {
+            None
+        } else {
+            let (_, record_size, _) = read_record_head(&self.bytes[.. 16]).unwrap();
+            Some(record_size as usize).filter(|&x| 16 + x <= self.bytes.len())
+        };
+        let Some(record_size) = record_size else {
+            let bytes = std::mem::take(&mut self.bytes);
+            self.offset += bytes.len() as u64;
+            return Some(Err(ReadRecordError {
+                source: Right(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                bytes: bytes.to_vec()
+            }));
+        };
+        let (record_bytes, rest) = self.bytes.split_at(16 + record_size);
+        let offset = self.offset;
+        self.bytes = rest;
+        self.offset += record_bytes.len() as u64;
+        let (tag, _, flags) = read_record_head(&record_bytes[.. 16]).unwrap();
+        let Some(flags) = RecordFlags::from_bits(flags) else {
+            return Some(Err(ReadRecordError {
+                source: Left(RecordError::UnknownRecordFlags(UnknownRecordFlags {
+                    record_offset: offset,
+                    record_tag: tag,
+                    value: flags
+                })),
+                bytes: record_bytes.to_vec()
+            }));
+        };
+        Some(Ok(RecordRef { offset, tag, flags, record: record_bytes, omwsave: self.omwsave, zip_mode: self.zip_mode }))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub records: Vec<Record>,
+    pub errors: Vec<Either<RecordError, io::Error>>,
+}
+
+pub fn validate_records(code_page: CodePage, mode: RecordReadMode, omwsave: bool, zip_mode: ZipMode, offset: u64, bytes: &[u8])
+    -> ValidationReport {
+
+    let mut report = ValidationReport::default();
+    for record in RecordRefs::new(omwsave, offset, bytes).with_zip_mode(zip_mode) {
+        match record {
+            Ok(record) => {
+                let (record, errors) = record.decode_all(code_page, mode);
+                report.records.push(record);
+                report.errors.extend(errors.into_iter().map(Left));
+            },
+            Err(e) => report.errors.push(e.into_source()),
+        }
+    }
+    report
+}
+
+pub fn read_records_parallel(
+    code_page: CodePage, mode: RecordReadMode, omwsave: bool, zip_mode: ZipMode, offset: u64, bytes: &[u8]
+) -> Vec<Result<Record, ReadRecordError>> {
+
+    let records = RecordRefs::new(omwsave, offset, bytes).with_zip_mode(zip_mode).collect::<Vec<_>>();
+    let threads = thread::available_parallelism().map_or(1, |x| x.get());
+    let chunk_size = records.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let chunks = records.chunks(chunk_size).map(|chunk| scope.spawn(move || {
+            chunk.iter().map(|record| match record {
+                Err(e) => Err(ReadRecordError {
+                    source: e.source.as_ref().map_left(Clone::clone).map_right(|x| io::Error::from(x.kind())),
+                    bytes: e.bytes.clone()
+                }),
+                Ok(record) => record.decode(code_page, mode).map_err(|record_error| ReadRecordError {
+                    source: Left(record_error),
+                    bytes: record.record.to_vec()
+                }),
+            }).collect::<Vec<_>>()
+        })).collect::<Vec<_>>();
+        chunks.into_iter().flat_map(|x| x.join().unwrap()).collect()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -2500,7 +3099,7 @@ mod tests {
         input.extend(DELE.dword.to_le_bytes().iter());
         input.extend(6u32.to_le_bytes().iter());
         input.extend([0x00, 0x00, 0x00, 0x00, 0x00, 0x00].iter());
-        let result = field(CodePage::English, RecordReadMode::Strict, DIAL, META, false)(&input);
+        let result = field(CodePage::English, RecordReadMode::Strict, DIAL, META, false, ZipMode::Compressed)(&input);
         let error = result.err().unwrap();
         if let nom::Err::Failure(FieldError::FieldSizeMismatch(DELE, expected, actual)) = error {
             assert_eq!(expected, 4);
@@ -2516,7 +3115,7 @@ mod tests {
         input.extend(DELE.dword.to_le_bytes().iter());
         input.extend(2u32.to_le_bytes().iter());
         input.extend([0x00, 0x00, 0x00, 0x00, 0x00, 0x00].iter());
-        let result = field(CodePage::English, RecordReadMode::Strict, DIAL, META, false)(&input);
+        let result = field(CodePage::English, RecordReadMode::Strict, DIAL, META, false, ZipMode::Compressed)(&input);
         let error = result.err().unwrap();
         if let nom::Err::Error(FieldError::FieldSizeMismatch(DELE, expected, actual)) = error {
             assert_eq!(expected, 4);
@@ -2532,7 +3131,7 @@ mod tests {
         input.extend(DELE.dword.to_le_bytes().iter());
         input.extend(2u32.to_le_bytes().iter());
         input.extend([0x00, 0x00].iter());
-        let result = field(CodePage::English, RecordReadMode::Strict, DIAL, META, false)(&input);
+        let result = field(CodePage::English, RecordReadMode::Strict, DIAL, META, false, ZipMode::Compressed)(&input);
         let error = result.err().unwrap();
         if let nom::Err::Error(FieldError::FieldSizeMismatch(DELE, expected, actual)) = error {
             assert_eq!(expected, 4);
@@ -2546,7 +3145,7 @@ mod tests {
     fn read_string_list_field() {
         let input: &'static [u8] = b"123\r\n\xC0\xC1t\r\n\xDA\xDFX\r\n";
         if let (remaining_input, Field::StringList(result)) =
-                field_body(CodePage::Russian, RecordReadMode::Strict, INFO, META, BNAM, input.len() as u32, false)(input).unwrap() {
+                field_body(CodePage::Russian, RecordReadMode::Strict, INFO, META, BNAM, input.len() as u32, false, ZipMode::Compressed)(input).unwrap() {
             assert_eq!(remaining_input.len(), 0);
             assert_eq!(result.len(), 4);
             assert_eq!(result[0], "123");
@@ -2561,13 +3160,13 @@ mod tests {
     #[test]
     fn read_from_vec() {
         let input: Vec<u8> = Vec::new();
-        field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false)(&input).err().unwrap();
+        field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false, ZipMode::Compressed)(&input).err().unwrap();
     }
 
     #[test]
     fn read_from_vec_if_let() {
         let input: Vec<u8> = Vec::new();
-        let res = field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false)(&input);
+        let res = field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false, ZipMode::Compressed)(&input);
         if let Ok((_, _)) = res {
             panic!()
         } else { }
@@ -2581,7 +3180,7 @@ mod tests {
         input.extend(string(&len(32, "author")));
         input.extend(string(&len(256, "description\r\nlines\r\n")));
         input.extend(vec![0x01, 0x02, 0x03, 0x04]);
-        let result = field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false)(&input);
+        let result = field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false, ZipMode::Compressed)(&input);
         if let (remaining_input, Field::FileMetadata(result)) = result.unwrap() {
             assert_eq!(remaining_input.len(), 0);
             assert_eq!(result.file_type, FileType::ESS);
@@ -2601,7 +3200,7 @@ mod tests {
         input.extend(string(&len(32, "author")));
         input.extend(string(&len(256, "description")));
         input.extend([0x01, 0x02, 0x03, 0x04].iter());
-        let result = field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false)(&input);
+        let result = field_body(CodePage::English, RecordReadMode::Strict, TES3, META, HEDR, input.len() as u32, false, ZipMode::Compressed)(&input);
         let error = result.err().unwrap();
         if let nom::Err::Failure(FieldBodyError::UnknownValue(Unknown::FileType(val), offset)) = error {
             assert_eq!(val, 0x100000);
@@ -2855,7 +3454,7 @@ mod tests {
                 ]))
             ]
         };
-        let bin: Vec<u8> = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false }), true)
+        let bin: Vec<u8> = code::serialize(&ValueWithSeed(&record, RecordSerde { code_page: Some(CodePage::English), omwsave: false, zip_mode: ZipMode::Compressed }), true)
             .unwrap();
         println!("{:?}", bin);
         let mut bin = &bin[..];
@@ -2891,4 +3490,213 @@ mod tests {
             panic!()
         }
     }
+
+    #[test]
+    fn borrowed_records() {
+        let mut input: Vec<u8> = Vec::new();
+        input.extend(CREA.dword.to_le_bytes().iter());
+        input.extend(54u32.to_le_bytes().iter());
+        input.extend(0u64.to_le_bytes().iter());
+        input.extend(NAME.dword.to_le_bytes().iter());
+        input.extend(6u32.to_le_bytes().iter());
+        input.extend(string("bear\0\0"));
+        input.extend(NPCS.dword.to_le_bytes().iter());
+        input.extend(32u32.to_le_bytes().iter());
+        input.extend(string(&len(32, "spell")));
+        input.extend(CREA.dword.to_le_bytes().iter());
+        let mut records = RecordRefs::new(false, 0, &input[..]);
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(record.tag(), CREA);
+        assert_eq!(record.size(), 54);
+        let fields = record.fields().map(|x| x.unwrap()).collect::<Vec<_>>();
+        assert_eq!(fields.iter().map(|x| x.tag()).collect::<Vec<_>>(), vec![NAME, NPCS]);
+        assert_eq!(fields[1].offset(), 14);
+        assert_eq!(fields[0].bytes(), b"bear\0\0");
+        let spell = record.field(NPCS).unwrap().decode(CodePage::English, RecordReadMode::Strict).unwrap();
+        assert_eq!(spell, Field::String("spell".into()));
+        let mut reader = &input[..];
+        let owned = Records::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut reader).next().unwrap().unwrap();
+        assert_eq!(record.decode(CodePage::English, RecordReadMode::Strict).unwrap(), owned);
+        let error = records.next().unwrap().err().unwrap();
+        assert_eq!(error.as_io_error().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(error.as_bytes().len(), 4);
+        assert!(records.next().is_none());
+        assert_eq!(records.offset(), 74);
+    }
+
+    #[test]
+    fn indexed_records() {
+        let mut input: Vec<u8> = Vec::new();
+        for name in ["a", "b"] {
+            input.extend(MISC.dword.to_le_bytes().iter());
+            input.extend(10u32.to_le_bytes().iter());
+            input.extend(0u64.to_le_bytes().iter());
+            input.extend(NAME.dword.to_le_bytes().iter());
+            input.extend(2u32.to_le_bytes().iter());
+            input.extend(string(&len(2, name)));
+        }
+        let mut input = io::Cursor::new(input);
+        let index = RecordIndex::new(&mut input).unwrap();
+        assert_eq!(index.records(), &[
+            IndexedRecord { tag: MISC, flags: RecordFlags::empty(), offset: 0, size: 10 },
+            IndexedRecord { tag: MISC, flags: RecordFlags::empty(), offset: 26, size: 10 },
+        ]);
+        let record = index.read(1, CodePage::English, RecordReadMode::Strict, false, ZipMode::Compressed, &mut input).unwrap();
+        assert_eq!(record.fields, vec![(NAME, Field::StringZ("b".into()))]);
+        let error = index.read(3, CodePage::English, RecordReadMode::Strict, false, ZipMode::Compressed, &mut input).unwrap_err();
+        assert_eq!(error.as_io_error().kind(), io::ErrorKind::InvalidInput);
+        input.get_mut().pop();
+        input.set_position(0);
+        assert_eq!(RecordIndex::new(&mut input).err().unwrap().as_io_error().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn parallel_records() {
+        let mut input: Vec<u8> = Vec::new();
+        for i in 0 .. 100 {
+            input.extend(MISC.dword.to_le_bytes().iter());
+            input.extend(12u32.to_le_bytes().iter());
+            input.extend(0u64.to_le_bytes().iter());
+            input.extend(NAME.dword.to_le_bytes().iter());
+            input.extend(4u32.to_le_bytes().iter());
+            input.extend(string(&len(4, &format!("{i:03}"))));
+        }
+        input[28 * 50 + 20] = 5;
+        let records = read_records_parallel(CodePage::English, RecordReadMode::Strict, false, ZipMode::Compressed, 0, &input[..]);
+        assert_eq!(records.len(), 100);
+        for (i, record) in records.into_iter().enumerate() {
+            if i == 50 {
+                let error = record.err().unwrap();
+                assert_eq!(error.source().left().unwrap().record_offset(), 28 * 50);
+                assert_eq!(error.as_bytes().len(), 28);
+            } else {
+                assert_eq!(record.unwrap().fields, vec![(NAME, Field::StringZ(format!("{i:03}").into()))]);
+            }
+        }
+    }
+
+    #[test]
+    fn recover_after_corrupt_record() {
+        let mut input: Vec<u8> = Vec::new();
+        for name in ["a", "b", "c"] {
+            input.extend(MISC.dword.to_le_bytes().iter());
+            input.extend(10u32.to_le_bytes().iter());
+            input.extend(0u64.to_le_bytes().iter());
+            input.extend(NAME.dword.to_le_bytes().iter());
+            input.extend(2u32.to_le_bytes().iter());
+            input.extend(string(&len(2, name)));
+        }
+        input[4] = 40;
+        let records = RecoveringRecords::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut &input[..])
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 3);
+        let skipped = records[0].as_ref().err().unwrap();
+        assert_eq!((skipped.offset, skipped.size), (0, 26));
+        assert_eq!(records[1].as_ref().unwrap().fields, vec![(NAME, Field::StringZ("b".into()))]);
+        assert_eq!(records[2].as_ref().unwrap().fields, vec![(NAME, Field::StringZ("c".into()))]);
+        input[30 .. 34].copy_from_slice(&0xF000_0000u32.to_le_bytes());
+        let records = RecoveringRecords::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut &input[..])
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        let skipped = records[0].as_ref().err().unwrap();
+        assert_eq!((skipped.offset, skipped.size), (0, 52));
+        assert_eq!(records[1].as_ref().unwrap().fields, vec![(NAME, Field::StringZ("c".into()))]);
+    }
+
+    struct BrokenInput<'a>(&'a [u8]);
+
+    impl<'a> Read for BrokenInput<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() { return Err(io::Error::other("broken input")); }
+            Read::read(&mut self.0, buf)
+        }
+    }
+
+    #[test]
+    fn recover_reports_resync_error() {
+        let mut input: Vec<u8> = Vec::new();
+        input.extend(MISC.dword.to_le_bytes().iter());
+        input.extend(10u32.to_le_bytes().iter());
+        input.extend(0u64.to_le_bytes().iter());
+        input.extend(NAME.dword.to_le_bytes().iter());
+        input.extend(9u32.to_le_bytes().iter());
+        input.extend(string(&len(2, "a")));
+        let mut input = BrokenInput(&input[..]);
+        let records = RecoveringRecords::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut input)
+            .take(2).collect::<Vec<_>>();
+        let skipped = records[0].as_ref().err().unwrap();
+        assert_eq!((skipped.offset, skipped.size), (0, 26));
+        let failed = records[1].as_ref().err().unwrap();
+        assert_eq!((failed.offset, failed.size), (26, 0));
+        assert_eq!(failed.error.as_io_error().kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn field_head_is_not_record_head() {
+        let mut head: Vec<u8> = Vec::new();
+        head.extend(NAME.dword.to_le_bytes().iter());
+        head.extend(10u32.to_le_bytes().iter());
+        head.extend(0u64.to_le_bytes().iter());
+        assert_eq!(plausible_record_size(&head), None);
+        head[.. 4].copy_from_slice(&MISC.dword.to_le_bytes());
+        assert_eq!(plausible_record_size(&head), Some(10));
+        head[4 .. 8].copy_from_slice(&0xF000_0000u32.to_le_bytes());
+        assert_eq!(plausible_record_size(&head), None);
+        let mut body: Vec<u8> = Vec::new();
+        body.extend(NAME.dword.to_le_bytes().iter());
+        body.extend(2u32.to_le_bytes().iter());
+        body.extend(string("a\0"));
+        assert!(is_plausible_record_body(&body));
+        body.extend(DATA.dword.to_le_bytes().iter());
+        body.extend(4u32.to_le_bytes().iter());
+        assert!(!is_plausible_record_body(&body));
+        body.extend(0u32.to_le_bytes().iter());
+        assert!(is_plausible_record_body(&body));
+        body.extend(0u16.to_le_bytes().iter());
+        assert!(!is_plausible_record_body(&body));
+    }
+
+    #[test]
+    fn validate_all_fields() {
+        let mut input: Vec<u8> = Vec::new();
+        input.extend(CREA.dword.to_le_bytes().iter());
+        input.extend(36u32.to_le_bytes().iter());
+        input.extend(0u64.to_le_bytes().iter());
+        input.extend(NAME.dword.to_le_bytes().iter());
+        input.extend(6u32.to_le_bytes().iter());
+        input.extend(string("bear\0\0"));
+        input.extend(FLAG.dword.to_le_bytes().iter());
+        input.extend(4u32.to_le_bytes().iter());
+        input.extend(0xFFFF_FFFFu32.to_le_bytes().iter());
+        input.extend(XSCL.dword.to_le_bytes().iter());
+        input.extend(2u32.to_le_bytes().iter());
+        input.extend(0u16.to_le_bytes().iter());
+        let report = validate_records(CodePage::English, RecordReadMode::Strict, false, ZipMode::Compressed, 0, &input[..]);
+        assert_eq!(report.records.len(), 1);
+        assert_eq!(report.records[0].fields[0], (NAME, Field::StringZ("bear\0".into())));
+        assert_eq!(report.records[0].fields[1], (FLAG, Field::U8List(vec![0xFF; 4])));
+        assert_eq!(report.records[0].fields[2], (XSCL, Field::U8List(vec![0; 2])));
+        assert_eq!(report.errors.len(), 2);
+        let Left(RecordError::UnknownValue(unknown)) = &report.errors[0] else { panic!() };
+        assert_eq!(unknown.field_offset, 14);
+        let Left(RecordError::FieldSizeMismatch(mismatch)) = &report.errors[1] else { panic!() };
+        assert_eq!(mismatch.field_tag, XSCL);
+    }
+
+    #[test]
+    fn record_spans() {
+        let mut input: Vec<u8> = Vec::new();
+        for name in ["a", "bc"] {
+            input.extend(MISC.dword.to_le_bytes().iter());
+            input.extend((9 + name.len() as u32).to_le_bytes().iter());
+            input.extend(0u64.to_le_bytes().iter());
+            input.extend(NAME.dword.to_le_bytes().iter());
+            input.extend((1 + name.len() as u32).to_le_bytes().iter());
+            input.extend(string(&len(name.len() + 1, name)));
+        }
+        let mut input = &input[..];
+        let spans = Records::new(CodePage::English, RecordReadMode::Strict, false, 0x100, &mut input).spans()
+            .map(|x| x.unwrap()).map(|x| (x.offset, x.size)).collect::<Vec<_>>();
+        assert_eq!(spans, vec![(0x100, 26), (0x11A, 27)]);
+    }
 }
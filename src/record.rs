@@ -6,8 +6,6 @@ use crate::strings::*;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as base64_STANDARD;
 use either::{Either, Left, Right};
-use flate2::Compression;
-use flate2::write::{ZlibDecoder, ZlibEncoder};
 use nameof::name_of;
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::ser::Error as ser_Error;
@@ -15,8 +13,9 @@ use serde::ser::{SerializeMap, SerializeSeq};
 use serde::de::{self, DeserializeSeed, Unexpected, VariantAccess};
 use serde::de::Error as de_Error;
 use serde_serialize_seed::{SerializeSeed, ValueWithSeed, VecSerde};
+use std::borrow::Cow;
 use std::fmt::{self, Debug, Formatter};
-use std::io::Write;
+use std::io;
 
 bitflags_ext! {
     pub struct RecordFlags: u64 {
@@ -35,6 +34,17 @@ pub struct Record {
     pub fields: Vec<(Tag, Field)>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum RecordId {
+    Singleton,
+    Name(String),
+    Interior(String),
+    Exterior(Grid),
+    Skill(Skill),
+    Effect(EffectIndex),
+    Reference(i32),
+}
+
 impl Record {
     pub fn fit(&mut self, omwsave: bool) {
         let mut prev_tag = META;
@@ -43,6 +53,74 @@ impl Record {
             prev_tag = field_tag;
         }
     }
+
+    pub fn uncompressed_field(&self, tag: Tag, zip_mode: ZipMode, omwsave: bool) -> Option<io::Result<Cow<'_, [u8]>>> {
+        let mut prev_tag = META;
+        for (field_tag, field) in &self.fields {
+            if *field_tag == tag && FieldType::from_tags(self.tag, prev_tag, tag, omwsave) == FieldType::U8ListZip {
+                if let Field::U8List(bytes) = field {
+                    return Some(zip_mode.decode(bytes));
+                }
+            }
+            prev_tag = *field_tag;
+        }
+        None
+    }
+
+    fn id_field(&self, tag: Tag) -> Option<&Field> {
+        self.fields.iter().find(|x| x.0 == tag).map(|x| &x.1)
+    }
+
+    fn id_name(&self, tag: Tag) -> Option<String> {
+        match self.id_field(tag)? {
+            Field::StringZ(s) => Some(s.string.to_lowercase()),
+            Field::String(s) => Some(s.to_lowercase()),
+            _ => None
+        }
+    }
+
+    pub fn id(&self) -> Option<RecordId> {
+        match self.tag {
+            TES3 | GAME | FMAP | PCDT | JOUR | KLST | CAM_ | GMAP | PROJ | SPLM | CSTA | ENAB => Some(RecordId::Singleton),
+            INFO => self.id_name(INAM).map(RecordId::Name),
+            SCPT => match self.id_field(SCHD)? {
+                Field::ScriptMetadata(metadata) => Some(RecordId::Name(metadata.name.to_lowercase())),
+                _ => None
+            },
+            SKIL => match self.id_field(INDX)? {
+                &Field::Skill(skill) => Some(RecordId::Skill(skill)),
+                _ => None
+            },
+            MGEF => match self.id_field(INDX)? {
+                &Field::EffectIndex(effect) => Some(RecordId::Effect(effect)),
+                _ => None
+            },
+            CELL => match self.id_field(DATA)? {
+                Field::Cell(Cell { position: CellPosition::Exterior { x, y }, .. }) =>
+                    Some(RecordId::Exterior(Grid { x: *x, y: *y })),
+                Field::Cell(_) => self.id_name(NAME).map(RecordId::Interior),
+                _ => None
+            },
+            LAND => match self.id_field(INTV)? {
+                Field::Grid(grid) => Some(RecordId::Exterior(grid.clone())),
+                _ => None
+            },
+            PGRD => match self.id_field(DATA)? {
+                Field::PathGrid(path_grid) => match self.id_name(NAME) {
+                    Some(name) if !name.is_empty() && path_grid.grid == (Grid { x: 0, y: 0 }) =>
+                        Some(RecordId::Interior(name)),
+                    _ => Some(RecordId::Exterior(path_grid.grid.clone())),
+                },
+                _ => None
+            },
+            REFR => match self.id_field(FRMR)? {
+                Field::I32List(list) => list.first().copied().map(RecordId::Reference),
+                &Field::I32(index) => Some(RecordId::Reference(index)),
+                _ => None
+            },
+            _ => self.id_name(NAME).map(RecordId::Name),
+        }
+    }
 }
     
 struct FieldBodySerializer<'a> {
@@ -52,6 +130,7 @@ struct FieldBodySerializer<'a> {
     field_tag: Tag,
     field: &'a Field,
     omwsave: bool,
+    zip_mode: ZipMode,
 }
 
 impl<'a> Serialize for FieldBodySerializer<'a> {
@@ -96,13 +175,13 @@ impl<'a> Serialize for FieldBodySerializer<'a> {
             },
             FieldType::U8ListZip => if let Field::U8List(v) = self.field {
                 if serializer.is_human_readable() {
-                    base64_STANDARD.encode(v).serialize(serializer)
+                    let compressed = match self.zip_mode {
+                        ZipMode::Compressed => v.into(),
+                        ZipMode::Uncompressed => ZipMode::Compressed.encode(v),
+                    };
+                    base64_STANDARD.encode(compressed).serialize(serializer)
                 } else {
-                    let uncompressed = (|| {
-                        let mut decoder = ZlibDecoder::new(Vec::new());
-                        decoder.write_all(&v[..])?;
-                        decoder.finish()
-                    })().map_err(|_| S::Error::custom("invalid compressed data"))?;
+                    let uncompressed = self.zip_mode.decode(v).map_err(|_| S::Error::custom("invalid compressed data"))?;
                     uncompressed.serialize(serializer)
                 }
             } else {
@@ -456,7 +535,7 @@ impl<'a> Serialize for FieldBodySerializer<'a> {
     }
 }
 
-struct FieldSerializer<'a>(Option<CodePage>, Tag, Tag, Either<RecordFlags, (Tag, &'a Field)>, bool);
+struct FieldSerializer<'a>(Option<CodePage>, Tag, Tag, Either<RecordFlags, (Tag, &'a Field)>, bool, ZipMode);
 
 impl<'a> Serialize for FieldSerializer<'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
@@ -471,7 +550,7 @@ impl<'a> Serialize for FieldSerializer<'a> {
                 serializer.serialize_entry(&field_tag, &FieldBodySerializer {
                     code_page: self.0,
                     record_tag: self.1, prev_tag: self.2, field_tag, field,
-                    omwsave: self.4
+                    omwsave: self.4, zip_mode: self.5
                 })?;
             }
         };
@@ -479,7 +558,7 @@ impl<'a> Serialize for FieldSerializer<'a> {
     }
 }
 
-struct RecordBodySerializer<'a>(Option<CodePage>, &'a Record, bool);
+struct RecordBodySerializer<'a>(Option<CodePage>, &'a Record, bool, ZipMode);
 
 impl<'a> Serialize for RecordBodySerializer<'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
@@ -487,11 +566,11 @@ impl<'a> Serialize for RecordBodySerializer<'a> {
         let entry_count = self.1.fields.len() + if has_flags { 1 } else { 0 };
         let mut serializer = serializer.serialize_seq(Some(entry_count))?;
         if has_flags {
-            serializer.serialize_element(&FieldSerializer(self.0, self.1.tag, META, Left(self.1.flags), self.2))?;
+            serializer.serialize_element(&FieldSerializer(self.0, self.1.tag, META, Left(self.1.flags), self.2, self.3))?;
         }
         let mut prev_tag = META;
         for &(field_tag, ref field) in &self.1.fields {
-            serializer.serialize_element(&FieldSerializer(self.0, self.1.tag, prev_tag, Right((field_tag, field)), self.2))?;
+            serializer.serialize_element(&FieldSerializer(self.0, self.1.tag, prev_tag, Right((field_tag, field)), self.2, self.3))?;
             prev_tag = field_tag;
         }
         serializer.end()
@@ -502,6 +581,7 @@ impl<'a> Serialize for RecordBodySerializer<'a> {
 pub struct RecordSerde {
     pub code_page: Option<CodePage>,
     pub omwsave: bool,
+    pub zip_mode: ZipMode,
 }
 
 impl SerializeSeed for RecordSerde {
@@ -511,9 +591,9 @@ impl SerializeSeed for RecordSerde {
         let is_human_readable = serializer.is_human_readable();
         let mut serializer = serializer.serialize_map(Some(1))?;
         if is_human_readable {
-            serializer.serialize_entry(&value.tag, &RecordBodySerializer(self.code_page, value, self.omwsave))?;
+            serializer.serialize_entry(&value.tag, &RecordBodySerializer(self.code_page, value, self.omwsave, self.zip_mode))?;
         } else {
-            serializer.serialize_entry(&(value.tag, value.flags), &RecordBodySerializer(self.code_page, value, self.omwsave))?;
+            serializer.serialize_entry(&(value.tag, value.flags), &RecordBodySerializer(self.code_page, value, self.omwsave, self.zip_mode))?;
         }
         serializer.end()
     }
@@ -531,17 +611,44 @@ impl<'de> de::Visitor<'de> for Base64DeVisitor {
     }
 }
 
-struct ZlibEncoderDeVisitor;
+struct ZipDeVisitor(ZipMode);
 
-impl<'de> de::Visitor<'de> for ZlibEncoderDeVisitor {
+impl<'de> de::Visitor<'de> for ZipDeVisitor {
     type Value = Vec<u8>;
 
     fn expecting(&self, f: &mut Formatter) -> fmt::Result { write!(f, "bytes") }
 
     fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E> where E: de::Error {
-        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(5));
-        encoder.write_all(bytes).unwrap();
-        Ok(encoder.finish().unwrap())
+        Ok(self.0.encode(bytes).into_owned())
+    }
+}
+
+struct LegacyStringZDeVisitor(Option<CodePage>);
+
+impl<'de> de::Visitor<'de> for LegacyStringZDeVisitor {
+    type Value = StringZ;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result { write!(f, "zero-terminated string or byte list") }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E> where E: de::Error {
+        StringZSerde { code_page: self.0 }.deserialize(de::value::StrDeserializer::new(s))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: de::SeqAccess<'de> {
+        let mut bytes = Vec::new();
+        while let Some(b) = seq.next_element::<u8>()? {
+            bytes.push(b);
+        }
+        let has_tail_zero = bytes.last() == Some(&0);
+        if has_tail_zero {
+            bytes.pop();
+        }
+        let string = match self.0 {
+            Some(code_page) => code_page.decode(&bytes),
+            None if bytes.is_ascii() => bytes.into_iter().map(char::from).collect(),
+            None => return Err(A::Error::custom("code page required for non-ASCII byte list string")),
+        };
+        Ok(StringZ { string, has_tail_zero })
     }
 }
 
@@ -551,6 +658,7 @@ struct FieldBodyDeserializer {
     prev_tag: Tag,
     field_tag: Tag,
     omwsave: bool,
+    zip_mode: ZipMode,
 }
 
 impl<'de> DeserializeSeed<'de> for FieldBodyDeserializer {
@@ -567,6 +675,8 @@ impl<'de> DeserializeSeed<'de> for FieldBodyDeserializer {
                     StringSerde {
                         code_page: self.code_page, len: len.map(|x| x.try_into().unwrap())
                     }.deserialize(deserializer).map(Field::String),
+                FieldType::StringZ if self.field_tag == CNDT && deserializer.is_human_readable() =>
+                    deserializer.deserialize_any(LegacyStringZDeVisitor(self.code_page)).map(Field::StringZ),
                 FieldType::StringZ =>
                     StringZSerde { code_page: self.code_page }.deserialize(deserializer).map(Field::StringZ),
                 FieldType::Multiline(newline) =>
@@ -579,9 +689,15 @@ impl<'de> DeserializeSeed<'de> for FieldBodyDeserializer {
                 FieldType::ScriptData =>
                     ScriptDataSerde { code_page: self.code_page, lenient: true }.deserialize(deserializer).map(Field::ScriptData),
                 FieldType::U8ListZip => if deserializer.is_human_readable() {
-                    deserializer.deserialize_str(Base64DeVisitor)
+                    let compressed = deserializer.deserialize_str(Base64DeVisitor)?;
+                    match self.zip_mode {
+                        ZipMode::Compressed => Ok(compressed),
+                        ZipMode::Uncompressed => ZipMode::Compressed.decode(&compressed)
+                            .map(|x| x.into_owned())
+                            .map_err(|_| D::Error::custom("invalid compressed data")),
+                    }
                 } else {
-                    deserializer.deserialize_bytes(ZlibEncoderDeVisitor)
+                    deserializer.deserialize_bytes(ZipDeVisitor(self.zip_mode))
                 }.map(Field::U8List),
                 FieldType::Info => Info::deserialize(deserializer).map(Field::Info),
                 FieldType::Item => ItemSerde { code_page: self.code_page }.deserialize(deserializer).map(Field::Item),
@@ -670,6 +786,7 @@ struct FieldDeserializer {
     record_tag: Tag,
     prev_tag: Tag,
     omwsave: bool,
+    zip_mode: ZipMode,
 }
 
 impl<'de> de::Visitor<'de> for FieldDeserializer {
@@ -687,7 +804,7 @@ impl<'de> de::Visitor<'de> for FieldDeserializer {
         let body = map.next_value_seed(FieldBodyDeserializer {
             code_page: self.code_page,
             record_tag: self.record_tag, prev_tag: self.prev_tag, field_tag,
-            omwsave: self.omwsave,
+            omwsave: self.omwsave, zip_mode: self.zip_mode,
         })?;
         if map.next_key::<Tag>()?.is_some() {
             return Err(A::Error::custom("duplicated field tag"));
@@ -711,6 +828,7 @@ struct RecordBodyDeserializer {
     record_tag: Tag,
     record_flags: Option<RecordFlags>,
     omwsave: bool,
+    zip_mode: ZipMode,
 }
 
 impl<'de> de::Visitor<'de> for RecordBodyDeserializer {
@@ -727,7 +845,7 @@ impl<'de> de::Visitor<'de> for RecordBodyDeserializer {
         let mut prev_tag = META;
         while let Some(field) = seq.next_element_seed(FieldDeserializer {
             code_page: self.code_page, record_tag: self.record_tag, prev_tag,
-            omwsave: self.omwsave,
+            omwsave: self.omwsave, zip_mode: self.zip_mode,
         })? {
             match field {
                 Left(flags) => {
@@ -759,6 +877,7 @@ struct RecordVisitor {
     code_page: Option<CodePage>,
     is_human_readable: bool,
     omwsave: bool,
+    zip_mode: ZipMode,
 }
 
 impl<'de> de::Visitor<'de> for RecordVisitor {
@@ -778,7 +897,9 @@ impl<'de> de::Visitor<'de> for RecordVisitor {
                 .ok_or_else(|| A::Error::custom("missed record tag and flags"))?;
             (record_tag, Some(record_flags))
         };
-        let body = map.next_value_seed(RecordBodyDeserializer { code_page: self.code_page, record_tag, record_flags, omwsave: self.omwsave })?;
+        let body = map.next_value_seed(RecordBodyDeserializer {
+            code_page: self.code_page, record_tag, record_flags, omwsave: self.omwsave, zip_mode: self.zip_mode
+        })?;
         if map.next_key::<Tag>()?.is_some() {
             return Err(A::Error::custom("duplicated record tag"));
         }
@@ -791,7 +912,9 @@ impl<'de> DeserializeSeed<'de> for RecordSerde {
 
     fn deserialize<D>(self, deserializer: D) -> Result<Record, D::Error> where D: Deserializer<'de> {
         let is_human_readable = deserializer.is_human_readable();
-        deserializer.deserialize_map(RecordVisitor { code_page: self.code_page, is_human_readable, omwsave: self.omwsave } )
+        deserializer.deserialize_map(RecordVisitor {
+            code_page: self.code_page, is_human_readable, omwsave: self.omwsave, zip_mode: self.zip_mode
+        })
     }
 }
 
@@ -998,6 +1121,7 @@ impl<'de> Deserialize<'de> for PosRotOrCell {
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use crate::read::*;
     use serde::de::DeserializeSeed; 
     use serde_serialize_seed::ValueWithSeed;
     use std::str::FromStr;
@@ -1013,6 +1137,46 @@ mod tests {
         RecordFlags::DELETED.hash(&mut hasher);
     }
 
+    #[test]
+    fn record_ids() {
+        let cell = |name: &str, position| Record {
+            tag: CELL,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (NAME, Field::StringZ(name.into())),
+                (DATA, Field::Cell(Cell { flags: CellFlags::empty(), position })),
+            ]
+        };
+        assert_eq!(
+            cell("Balmora", CellPosition::Exterior { x: -3, y: -2 }).id(),
+            Some(RecordId::Exterior(Grid { x: -3, y: -2 }))
+        );
+        assert_eq!(
+            cell("Balmora, Guild of Mages", CellPosition::Interior { x: 0.0, y: 0.0 }).id(),
+            Some(RecordId::Interior("balmora, guild of mages".into()))
+        );
+        let path_grid = |name: &str, x, y| Record {
+            tag: PGRD,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (DATA, Field::PathGrid(PathGrid { grid: Grid { x, y }, flags: 1024, points: 0 })),
+                (NAME, Field::StringZ(name.into())),
+            ]
+        };
+        assert_eq!(path_grid("Ascadian Isles Region", -3, -2).id(), Some(RecordId::Exterior(Grid { x: -3, y: -2 })));
+        assert_eq!(path_grid("Bitter Coast Region", -3, -2).id(), path_grid("", -3, -2).id());
+        assert_eq!(path_grid("Balmora, Guild of Mages", 0, 0).id(), Some(RecordId::Interior("balmora, guild of mages".into())));
+        let skill = Record { tag: SKIL, flags: RecordFlags::empty(), fields: vec![(INDX, Field::Skill(Skill::Block))] };
+        assert_eq!(skill.id(), Some(RecordId::Skill(Skill::Block)));
+        let misc = Record {
+            tag: Tag::from_str("MISC").unwrap(),
+            flags: RecordFlags::empty(),
+            fields: vec![(NAME, Field::StringZ("Gold_001".into()))]
+        };
+        assert_eq!(misc.id(), Some(RecordId::Name("gold_001".into())));
+        assert_eq!(Record { tag: TES3, flags: RecordFlags::empty(), fields: Vec::new() }.id(), Some(RecordId::Singleton));
+    }
+
     #[test]
     fn test_record_flags() {
         assert_eq!("PERSIST", format!("{}", RecordFlags::PERSIST));
@@ -1044,8 +1208,8 @@ mod tests {
                 ]))
             ]
         };
-        let yaml = serde_yaml::to_string(&ValueWithSeed(&record, RecordSerde { code_page: None, omwsave: false })).unwrap();
-        let res = RecordSerde { code_page: None, omwsave: false }.deserialize(serde_yaml::Deserializer::from_str(&yaml)).unwrap();
+        let yaml = serde_yaml::to_string(&ValueWithSeed(&record, RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed })).unwrap();
+        let res = RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }.deserialize(serde_yaml::Deserializer::from_str(&yaml)).unwrap();
         assert_eq!(res.tag, record.tag);
         assert_eq!(res.flags, record.flags);
         assert_eq!(res.fields.len(), 2);
@@ -1070,4 +1234,53 @@ mod tests {
             panic!()
         }
     }
+
+    #[test]
+    fn uncompressed_zip_fields() {
+        let heights = (0 .. 100u8).collect::<Vec<_>>();
+        let compressed = Record {
+            tag: LAND,
+            flags: RecordFlags::empty(),
+            fields: vec![(VHGT, Field::U8List(ZipMode::Compressed.encode(&heights).into_owned()))]
+        };
+        let uncompressed = Record {
+            tag: LAND,
+            flags: RecordFlags::empty(),
+            fields: vec![(VHGT, Field::U8List(heights.clone()))]
+        };
+        assert_eq!(compressed.uncompressed_field(VHGT, ZipMode::Compressed, false).unwrap().unwrap(), &heights[..]);
+        assert_eq!(uncompressed.uncompressed_field(VHGT, ZipMode::Uncompressed, false).unwrap().unwrap(), &heights[..]);
+        let serde = |zip_mode| RecordSerde { code_page: Some(CodePage::English), omwsave: false, zip_mode };
+        let bytes = code::serialize(&ValueWithSeed(&compressed, serde(ZipMode::Compressed)), false).unwrap();
+        assert_eq!(code::serialize(&ValueWithSeed(&uncompressed, serde(ZipMode::Uncompressed)), false).unwrap(), bytes);
+        let mut input = &bytes[..];
+        let read = Records::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut input)
+            .with_zip_mode(ZipMode::Uncompressed)
+            .next().unwrap().unwrap();
+        assert_eq!(read, uncompressed);
+        let yaml = serde_yaml::to_string(&ValueWithSeed(&uncompressed, RecordSerde {
+            code_page: None, omwsave: false, zip_mode: ZipMode::Uncompressed
+        })).unwrap();
+        let res = RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }
+            .deserialize(serde_yaml::Deserializer::from_str(&yaml)).unwrap();
+        assert_eq!(res, compressed);
+    }
+
+    #[test]
+    fn legacy_byte_list_cndt() {
+        let yaml = "\
+NPC_:
+- NAME: fargoth
+- CNDT: [83, 101, 121, 100, 97, 32, 78, 101, 101, 110, 0]
+";
+        let res = RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }
+            .deserialize(serde_yaml::Deserializer::from_str(yaml)).unwrap();
+        assert_eq!(res.fields[1], (CNDT, Field::StringZ("Seyda Neen".into())));
+        let yaml = serde_yaml::to_string(&ValueWithSeed(&res, RecordSerde {
+            code_page: None, omwsave: false, zip_mode: ZipMode::Compressed
+        })).unwrap();
+        let res_yaml = RecordSerde { code_page: None, omwsave: false, zip_mode: ZipMode::Compressed }
+            .deserialize(serde_yaml::Deserializer::from_str(&yaml)).unwrap();
+        assert_eq!(res_yaml, res);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::field::{eq_f32, float_32};
+use crate::field::{eq_f32, float_32, ScriptVars};
 use crate::code_page::CodePage;
 use crate::serde_helpers::HexDump;
 use educe::Educe;
@@ -14,6 +14,15 @@ use serde::ser::Error as ser_Error;
 use serde_serialize_seed::{SerializeSeed, ValueWithSeed};
 use std::fmt::{self, Formatter};
 
+mod checker;
+mod compiler;
+mod decompiler;
+mod expr;
+
+pub use checker::*;
+pub use compiler::*;
+pub use decompiler::*;
+
 macro_attr! {
     #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
     #[derive(Debug, N, EnumDisplay!, EnumFromStr!)]
@@ -156,6 +165,17 @@ fn write_u32(v: u32, res: &mut Vec<u8>) {
     res.push((v >> 24) as u8);
 }
 
+pub(crate) fn local_var_slot(vars: &ScriptVars, var_type: VarType, index: u16) -> Option<usize> {
+    let (offset, count) = match var_type {
+        VarType::Short => (0, vars.shorts),
+        VarType::Long => (vars.shorts, vars.longs),
+        VarType::Float => (vars.shorts + vars.longs, vars.floats),
+        VarType::Mystery => return None,
+    };
+    if index == 0 || u32::from(index) > count { return None; }
+    Some((offset + u32::from(index) - 1) as usize)
+}
+
 impl Var {
     fn write(&self, code_page: CodePage, res: &mut Vec<u8>) -> Result<(), String> {
         match self {
@@ -868,7 +888,7 @@ mod parser {
         })(input)
     }
 
-    fn string<'a>(code_page: CodePage) -> impl FnMut(&'a [u8]) -> NomRes<&'a [u8], String, (), !> {
+    pub fn string<'a>(code_page: CodePage) -> impl FnMut(&'a [u8]) -> NomRes<&'a [u8], String, (), !> {
         map(flat_map(le_u8(), |len| take(len.into())), move |x| code_page.decode(x))
     }
 
@@ -908,7 +928,7 @@ mod parser {
         map(seq_2(tag([b'G']), string(code_page)), |(_, var)| var)
     }
 
-    fn var<'a>(code_page: CodePage) -> impl FnMut(&'a [u8]) -> NomRes<&'a [u8], Var, (), !> {
+    pub fn var<'a>(code_page: CodePage) -> impl FnMut(&'a [u8]) -> NomRes<&'a [u8], Var, (), !> {
         alt_3(
             map(owner_var(code_page), |(owner, var_type, index)| Var::Local { owner: Some(owner), var_type, index }),
             map(local_var, |(var_type, index)| Var::Local { owner: None, var_type, index }),
@@ -0,0 +1,333 @@
+use super::*;
+use super::expr::*;
+use crate::view::ScriptRecord;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptMismatch {
+    MissingField(&'static str),
+    VarsCount { metadata: u32, vars: u32 },
+    VarTableSize { metadata: u32, actual: u32 },
+    DataSize { metadata: u32, actual: u32 },
+    UnknownBytecode { offset: u32, size: u32 },
+    UnknownLocal { stmt_index: usize, var_type: VarType, index: u16 },
+    InvalidExpression { stmt_index: usize },
+    SourceError(CompileError),
+    SourceVars { source: Vec<String>, vars: Vec<String> },
+    SourceMetadata { source: ScriptVars, metadata: ScriptVars },
+    StaleBytecode { stmt_index: usize },
+}
+
+impl Display for ScriptMismatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ScriptMismatch::MissingField(field) => write!(f, "missing {field} field"),
+            ScriptMismatch::VarsCount { metadata, vars } =>
+                write!(f, "variables count mismatch: {metadata} in SCHD, {vars} in SCVR"),
+            ScriptMismatch::VarTableSize { metadata, actual } =>
+                write!(f, "variables size mismatch: {metadata} in SCHD, {actual} in SCVR"),
+            ScriptMismatch::DataSize { metadata, actual } =>
+                write!(f, "bytecode size mismatch: {metadata} in SCHD, {actual} in SCDT"),
+            ScriptMismatch::UnknownBytecode { offset, size } =>
+                write!(f, "{size} bytes of unknown bytecode at {offset:X}h"),
+            ScriptMismatch::UnknownLocal { stmt_index, var_type, index } =>
+                write!(f, "undeclared {var_type} local variable #{index} in statement {stmt_index}"),
+            ScriptMismatch::InvalidExpression { stmt_index } => write!(f, "invalid expression in statement {stmt_index}"),
+            ScriptMismatch::SourceError(e) => write!(f, "source compilation failed: {e}"),
+            ScriptMismatch::SourceVars { source, vars } =>
+                write!(f, "variables mismatch: [{}] in SCTX, [{}] in SCVR", source.join(", "), vars.join(", ")),
+            ScriptMismatch::SourceMetadata { source, metadata } =>
+                write!(
+                    f, "variables count mismatch: {}/{}/{} in SCTX, {}/{}/{} in SCHD",
+                    source.shorts, source.longs, source.floats, metadata.shorts, metadata.longs, metadata.floats
+                ),
+            ScriptMismatch::StaleBytecode { stmt_index } =>
+                write!(f, "bytecode differs from source starting at statement {stmt_index}"),
+        }
+    }
+}
+
+fn args_locals(args: &FuncArgs, locals: &mut Vec<(VarType, u16)>) {
+    let mut float = |x: &Float| if let &Float::Var { var_type, index } = x {
+        locals.push((var_type, index));
+    };
+    match args {
+        FuncArgs::CharFloat(_, a) | FuncArgs::Float(a) | FuncArgs::FloatStr(a, _) | FuncArgs::StrIntFloatInt(_, _, a, _) =>
+            float(a),
+        FuncArgs::Float3Byte(a, _) | FuncArgs::Float3IntListByte(a, _, _) | FuncArgs::StrIntFloat3Byte(_, _, a, _) =>
+            a.iter().for_each(float),
+        FuncArgs::Float4(a) | FuncArgs::Float4Str(a, _) => a.iter().for_each(float),
+        FuncArgs::StrFloat2(_, a) => a.iter().for_each(float),
+        _ => { }
+    }
+    let vars = match args {
+        FuncArgs::VarStr(var, _) => std::slice::from_ref(var),
+        FuncArgs::TextVarListStrList(_, vars, _) => &vars[..],
+        _ => &[]
+    };
+    for var in vars {
+        if let &Var::Local { owner: None, var_type, index } = var {
+            locals.push((var_type, index));
+        }
+    }
+}
+
+fn stmt_expr(stmt: &Stmt) -> Option<&String> {
+    match (stmt.func, &stmt.args) {
+        (Func::If | Func::ElseIf, FuncArgs::ByteStr(_, expr)) | (Func::Set, FuncArgs::VarStr(_, expr)) => Some(expr),
+        _ => None
+    }
+}
+
+fn stmt_locals(code_page: CodePage, stmt: &Stmt) -> Option<Vec<(VarType, u16)>> {
+    let mut locals = Vec::new();
+    args_locals(&stmt.args, &mut locals);
+    let Some(expr) = stmt_expr(stmt) else { return Some(locals); };
+    for token in read_expr(code_page, expr)? {
+        match token {
+            ExprToken::Var(Var::Local { owner: None, var_type, index }) => locals.push((var_type, index)),
+            ExprToken::Func(_, stmt) => args_locals(&stmt.args, &mut locals),
+            _ => { }
+        }
+    }
+    Some(locals)
+}
+
+#[derive(Debug, PartialEq)]
+enum ExprShape {
+    Var(Var),
+    Func(Option<String>, Box<StmtShape>),
+    Number(f32),
+    Text(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct StmtShape {
+    stmt: Stmt,
+    expr: Vec<ExprShape>,
+}
+
+fn remote_shape(var: &mut Var) {
+    if let Var::Local { owner: Some(_), var_type, index } = var {
+        *var_type = VarType::Mystery;
+        *index = 0;
+    }
+}
+
+fn stmt_shape(code_page: CodePage, stmt: &Stmt) -> StmtShape {
+    let mut shape = StmtShape { stmt: stmt.clone(), expr: Vec::new() };
+    if let Some(tokens) = stmt_expr(stmt).and_then(|x| read_expr(code_page, x)) {
+        shape.expr = tokens.into_iter().map(|token| match token {
+            ExprToken::Var(mut var) => {
+                remote_shape(&mut var);
+                ExprShape::Var(var)
+            },
+            ExprToken::Func(reference, stmt) => ExprShape::Func(reference, Box::new(stmt_shape(code_page, &stmt))),
+            ExprToken::Text(text) => text.parse().map_or(ExprShape::Text(text), ExprShape::Number),
+        }).collect();
+        if let FuncArgs::ByteStr(_, expr) | FuncArgs::VarStr(_, expr) = &mut shape.stmt.args {
+            expr.clear();
+        }
+    }
+    match &mut shape.stmt.args {
+        FuncArgs::VarStr(var, _) => remote_shape(var),
+        FuncArgs::TextVarListStrList(_, vars, _) => vars.iter_mut().for_each(remote_shape),
+        _ => { }
+    }
+    shape
+}
+
+impl ScriptRecord {
+    pub fn check(&self, code_page: CodePage) -> Vec<ScriptMismatch> {
+        let mut mismatches = Vec::new();
+        let Some(metadata) = self.metadata() else {
+            return vec![ScriptMismatch::MissingField("SCHD")];
+        };
+        let vars = self.vars().map_or(&[][..], |x| &x.vec[..]);
+        let vars_count = metadata.vars.shorts + metadata.vars.longs + metadata.vars.floats;
+        if vars_count as usize != vars.len() {
+            mismatches.push(ScriptMismatch::VarsCount { metadata: vars_count, vars: vars.len() as u32 });
+        }
+        let var_table_size = vars.iter().map(|x| code_page.encode(x).map_or(x.len(), |x| x.len()) + 1).sum::<usize>();
+        if var_table_size != metadata.var_table_size as usize {
+            mismatches.push(ScriptMismatch::VarTableSize { metadata: metadata.var_table_size, actual: var_table_size as u32 });
+        }
+        if let Some(data) = self.data() {
+            match data.to_bytes(code_page, true) {
+                Ok(bytes) => {
+                    if bytes.len() != metadata.data_size as usize {
+                        mismatches.push(ScriptMismatch::DataSize { metadata: metadata.data_size, actual: bytes.len() as u32 });
+                    }
+                    if !data.raw.is_empty() {
+                        mismatches.push(ScriptMismatch::UnknownBytecode {
+                            offset: (bytes.len() - data.raw.len()) as u32,
+                            size: data.raw.len() as u32
+                        });
+                    }
+                },
+                Err(_) => mismatches.push(ScriptMismatch::UnknownBytecode { offset: 0, size: metadata.data_size }),
+            }
+            for (stmt_index, stmt) in data.stmts.iter().enumerate() {
+                let Some(locals) = stmt_locals(code_page, stmt) else {
+                    mismatches.push(ScriptMismatch::InvalidExpression { stmt_index });
+                    continue;
+                };
+                for (var_type, index) in locals {
+                    let declared = local_var_slot(&metadata.vars, var_type, index).is_some_and(|slot| slot < vars.len());
+                    if !declared {
+                        mismatches.push(ScriptMismatch::UnknownLocal { stmt_index, var_type, index });
+                    }
+                }
+            }
+        } else if metadata.data_size != 0 {
+            mismatches.push(ScriptMismatch::MissingField("SCDT"));
+        }
+        if let Some(text) = self.text() {
+            match ScriptData::compile_with(code_page, &text.join("\n"), &|_, _| Some((VarType::Mystery, 0))) {
+                Err(e) => mismatches.push(ScriptMismatch::SourceError(e)),
+                Ok(compiled) => {
+                    if !compiled.vars.vec.iter().map(|x| x.to_lowercase()).eq(vars.iter().map(|x| x.to_lowercase())) {
+                        mismatches.push(ScriptMismatch::SourceVars { source: compiled.vars.vec, vars: vars.to_vec() });
+                    }
+                    if compiled.metadata.vars != metadata.vars {
+                        mismatches.push(ScriptMismatch::SourceMetadata {
+                            source: compiled.metadata.vars.clone(),
+                            metadata: metadata.vars.clone()
+                        });
+                    }
+                    let stmts = self.data().map_or(&[][..], |x| &x.stmts[..]);
+                    let stale = compiled.data.stmts.iter().zip(stmts.iter())
+                        .position(|(a, b)| stmt_shape(code_page, a) != stmt_shape(code_page, b))
+                        .or_else(|| (compiled.data.stmts.len() != stmts.len()).then_some(compiled.data.stmts.len().min(stmts.len())));
+                    if let Some(stmt_index) = stale {
+                        mismatches.push(ScriptMismatch::StaleBytecode { stmt_index });
+                    }
+                },
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::script_data::*;
+
+    fn script_record(source: &str) -> Record {
+        let compiled = ScriptData::compile(CodePage::English, source).unwrap();
+        Record {
+            tag: SCPT,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (SCHD, Field::ScriptMetadata(compiled.metadata)),
+                (SCVR, Field::StringZList(compiled.vars)),
+                (SCDT, Field::ScriptData(compiled.data)),
+                (SCTX, Field::StringList(source.lines().map(|x| x.to_string()).collect())),
+            ]
+        }
+    }
+
+    #[test]
+    fn consistent_script() {
+        let record = script_record("Begin Test\nshort a\nset a to 1\nEnd");
+        assert_eq!(ScriptRecord::try_from(&record).unwrap().check(CodePage::English), Vec::new());
+    }
+
+    #[test]
+    fn stale_script() {
+        let mut record = script_record("Begin Test\nshort a\nset a to 1\nFadeOut a\nEnd");
+        record.fields[3].1 = Field::StringList(vec!["Begin Test".into(), "short a".into(), "set a to 2".into(), "End".into()]);
+        let Field::ScriptData(data) = &mut record.fields[2].1 else { panic!() };
+        data.stmts[1].args = FuncArgs::Float(Float::Var { var_type: VarType::Float, index: 1 });
+        let mismatches = ScriptRecord::try_from(&record).unwrap().check(CodePage::English);
+        assert_eq!(mismatches, vec![
+            ScriptMismatch::UnknownLocal { stmt_index: 1, var_type: VarType::Float, index: 1 },
+            ScriptMismatch::StaleBytecode { stmt_index: 0 },
+        ]);
+    }
+
+    const CS_SOURCE: &str = "\
+Begin DoorCounterScript
+short opened
+float timer
+if ( OnActivate == 1 )
+    if ( opened == 0 )
+        set opened to 1
+        MessageBox \"The door creaks open.\"
+    endif
+    Activate
+endif
+set timer to timer + 1.5
+End DoorCounterScript";
+
+    fn cs_script_record() -> Record {
+        let bytes: &[u8] = &[
+            b"\x06\x01\x05\x0D ( X\x18\x10 == 1 )" as &[u8],
+            b"\x06\x01\x02\x0D ( s\x01\x00 == 0 )",
+            b"\x05\x01s\x01\x00\x02 1",
+            b"\x00\x10\x15\x00The door creaks open.\x00\x00",
+            b"\x09\x01",
+            b"\x17\x10",
+            b"\x09\x01",
+            b"\x05\x01f\x01\x00\x0A f\x01\x00 1.5 +",
+            b"\x01\x01",
+        ].concat();
+        Record {
+            tag: SCPT,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (SCHD, Field::ScriptMetadata(ScriptMetadata {
+                    name: "DoorCounterScript".into(),
+                    vars: ScriptVars { shorts: 1, longs: 0, floats: 1 },
+                    data_size: bytes.len() as u32,
+                    var_table_size: 13,
+                })),
+                (SCVR, Field::StringZList(StringZList { vec: vec!["opened".into(), "timer".into()], has_tail_zero: true })),
+                (SCDT, Field::ScriptData(ScriptData::from_bytes(CodePage::English, bytes))),
+                (SCTX, Field::StringList(CS_SOURCE.lines().map(|x| x.to_string()).collect())),
+            ]
+        }
+    }
+
+    #[test]
+    fn compile_cs_script() {
+        let record = cs_script_record();
+        let script = ScriptRecord::try_from(&record).unwrap();
+        let compiled = ScriptData::compile(CodePage::English, CS_SOURCE).unwrap();
+        assert_eq!(&compiled.metadata, script.metadata().unwrap());
+        assert_eq!(&compiled.vars, script.vars().unwrap());
+        assert_eq!(
+            compiled.data.to_bytes(CodePage::English, false).unwrap(),
+            script.data().unwrap().to_bytes(CodePage::English, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn cs_compiled_script() {
+        let mut record = cs_script_record();
+        let data_size = ScriptRecord::try_from(&record).unwrap().metadata().unwrap().data_size;
+        assert_eq!(ScriptRecord::try_from(&record).unwrap().check(CodePage::English), Vec::new());
+        let timer = Var::Local { owner: None, var_type: VarType::Float, index: 1 };
+        let Field::ScriptData(data) = &mut record.fields[2].1 else { panic!() };
+        data.stmts[7].args = FuncArgs::VarStr(timer.clone(), " f\x01\x00 1.50 +".into());
+        assert_eq!(ScriptRecord::try_from(&record).unwrap().check(CodePage::English), vec![
+            ScriptMismatch::DataSize { metadata: data_size, actual: data_size + 1 },
+        ]);
+        let Field::ScriptData(data) = &mut record.fields[2].1 else { panic!() };
+        data.stmts[7].args = FuncArgs::VarStr(timer.clone(), " f\x01\x00 2.5 +".into());
+        assert_eq!(ScriptRecord::try_from(&record).unwrap().check(CodePage::English), vec![
+            ScriptMismatch::StaleBytecode { stmt_index: 7 },
+        ]);
+        let Field::ScriptData(data) = &mut record.fields[2].1 else { panic!() };
+        data.stmts[7].args = FuncArgs::VarStr(timer, " f\x01\x00 1.5 +".into());
+        data.stmts[1].args = FuncArgs::ByteStr(2, "( opened == 0 )".into());
+        data.stmts[2].args = FuncArgs::VarStr(Var::Local { owner: None, var_type: VarType::Short, index: 1 }, " s\x02\x00".into());
+        assert_eq!(ScriptRecord::try_from(&record).unwrap().check(CodePage::English), vec![
+            ScriptMismatch::DataSize { metadata: data_size, actual: data_size + 4 },
+            ScriptMismatch::InvalidExpression { stmt_index: 1 },
+            ScriptMismatch::UnknownLocal { stmt_index: 2, var_type: VarType::Short, index: 2 },
+            ScriptMismatch::StaleBytecode { stmt_index: 1 },
+        ]);
+    }
+}
@@ -0,0 +1,701 @@
+use super::*;
+use super::expr::*;
+use crate::field::ScriptMetadata;
+use crate::strings::StringZList;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CompiledScript {
+    pub metadata: ScriptMetadata,
+    pub vars: StringZList,
+    pub data: ScriptData,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl Error for CompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { None }
+}
+
+static FUNCS: LazyLock<HashMap<String, Func>> = LazyLock::new(||
+    (0 ..= u16::MAX).filter_map(Func::n).map(|func| (func.to_string().to_lowercase(), func)).collect()
+);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token<'a> {
+    Str(&'a str),
+    Word(&'a str),
+    Arrow,
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[.. i],
+            _ => { }
+        }
+    }
+    line
+}
+
+fn tokenize(line: &str) -> Result<Vec<(usize, Token<'_>)>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    loop {
+        let trimmed = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let start = line.len() - trimmed.len();
+        rest = trimmed;
+        if rest.is_empty() { break; }
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or_else(|| "unterminated string".to_string())?;
+            tokens.push((start, Token::Str(&quoted[.. end])));
+            rest = &quoted[end + 1 ..];
+        } else if let Some(after) = rest.strip_prefix("->") {
+            tokens.push((start, Token::Arrow));
+            rest = after;
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == ',' || c == '"').unwrap_or(rest.len());
+            let end = rest[.. end].find("->").unwrap_or(end);
+            tokens.push((start, Token::Word(&rest[.. end])));
+            rest = &rest[end ..];
+        }
+    }
+    Ok(tokens)
+}
+
+const VAR_TYPES: [VarType; 3] = [VarType::Short, VarType::Long, VarType::Float];
+
+struct Vars<'r> {
+    names: [Vec<String>; 3],
+    globals: RefCell<Vec<String>>,
+    remote_vars: &'r dyn Fn(&str, &str) -> Option<(VarType, u16)>,
+}
+
+impl<'r> Vars<'r> {
+    fn find(&self, name: &str) -> Option<(VarType, u16)> {
+        for (var_type, names) in VAR_TYPES.into_iter().zip(self.names.iter()) {
+            if let Some(index) = names.iter().position(|x| x.eq_ignore_ascii_case(name)) {
+                return Some((var_type, (index + 1) as u16));
+            }
+        }
+        None
+    }
+
+    fn remote(&self, owner: &str, name: &str) -> Result<Var, String> {
+        let (var_type, index) = (self.remote_vars)(owner, name)
+            .ok_or_else(|| format!("unknown remote variable '{owner}.{name}'"))?;
+        Ok(Var::Local { owner: Some(owner.to_string()), var_type, index })
+    }
+
+    fn var(&self, name: &str) -> Result<Var, String> {
+        if let Some((var_type, index)) = self.find(name) {
+            return Ok(Var::Local { owner: None, var_type, index });
+        }
+        if let Some((owner, name)) = name.split_once('.') {
+            return self.remote(owner, name);
+        }
+        self.globals.borrow_mut().push(name.to_string());
+        Ok(Var::Global { name: name.to_string() })
+    }
+}
+
+struct Args<'a, 'b> {
+    tokens: &'b [(usize, Token<'a>)],
+    vars: &'b Vars<'b>,
+}
+
+impl<'a, 'b> Args<'a, 'b> {
+    fn next(&mut self) -> Result<&'b Token<'a>, String> {
+        let (first, rest) = self.tokens.split_first().ok_or_else(|| "missing argument".to_string())?;
+        self.tokens = rest;
+        Ok(&first.1)
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Str(s) | Token::Word(s) => Ok(s.to_string()),
+            Token::Arrow => Err("unexpected '->'".into()),
+        }
+    }
+
+    fn word(&mut self) -> Result<&'a str, String> {
+        match self.next()? {
+            &Token::Word(s) => Ok(s),
+            _ => Err("number or variable expected".into()),
+        }
+    }
+
+    fn int<T: FromStr>(&mut self) -> Result<T, String> {
+        let word = self.word()?;
+        word.parse().map_err(|_| format!("invalid integer '{word}'"))
+    }
+
+    fn float(&mut self) -> Result<Float, String> {
+        let word = self.word()?;
+        if let Ok(val) = word.parse() {
+            return Ok(Float::Val { val });
+        }
+        let (var_type, index) = self.vars.find(word).ok_or_else(|| format!("unknown local variable '{word}'"))?;
+        Ok(Float::Var { var_type, index })
+    }
+
+    fn floats<const N: usize>(&mut self) -> Result<[Float; N], String> {
+        let mut res = [Float::Val { val: 0.0 }; N];
+        for x in &mut res {
+            *x = self.float()?;
+        }
+        Ok(res)
+    }
+
+    fn var(&mut self) -> Result<Var, String> {
+        match self.next()? {
+            &Token::Str(owner) => {
+                let word = self.word()?;
+                let name = word.strip_prefix('.').ok_or_else(|| format!("invalid remote variable '{word}'"))?;
+                self.vars.remote(owner, name)
+            },
+            &Token::Word(word) => self.vars.var(word),
+            Token::Arrow => Err("unexpected '->'".into()),
+        }
+    }
+
+    fn end(&self) -> Result<(), String> {
+        if self.tokens.is_empty() { Ok(()) } else { Err("too many arguments".into()) }
+    }
+
+    fn func_args(&mut self, params: FuncParams) -> Result<FuncArgs, String> {
+        let args = self.args(params)?;
+        self.end()?;
+        Ok(args)
+    }
+
+    fn args(&mut self, params: FuncParams) -> Result<FuncArgs, String> {
+        Ok(match params {
+            FuncParams::None => FuncArgs::None,
+            FuncParams::Byte => FuncArgs::Byte(self.int()?),
+            FuncParams::ByteStr => FuncArgs::ByteStr(self.int()?, self.str()?),
+            FuncParams::CharFloat => FuncArgs::CharFloat(self.str()?, self.float()?),
+            FuncParams::Float => FuncArgs::Float(self.float()?),
+            FuncParams::FloatStr => FuncArgs::FloatStr(self.float()?, self.str()?),
+            FuncParams::Float3Byte => FuncArgs::Float3Byte(self.floats()?, self.int()?),
+            FuncParams::Float3IntListByte => {
+                let floats = self.floats()?;
+                let mut ints = Vec::new();
+                while self.tokens.len() > 1 {
+                    ints.push(self.int()?);
+                }
+                FuncArgs::Float3IntListByte(floats, ints, self.int()?)
+            },
+            FuncParams::Float4 => FuncArgs::Float4(self.floats()?),
+            FuncParams::Float4Str => FuncArgs::Float4Str(self.floats()?, self.str()?),
+            FuncParams::Int => FuncArgs::Int(self.int()?),
+            FuncParams::IntByte => FuncArgs::IntByte(self.int()?, self.int()?),
+            FuncParams::Int2 => FuncArgs::Int2([self.int()?, self.int()?]),
+            FuncParams::Str => FuncArgs::Str(self.str()?),
+            FuncParams::StrByte => FuncArgs::StrByte(self.str()?, self.int()?),
+            FuncParams::StrByte8 => {
+                let s = self.str()?;
+                let mut bytes = [0; 8];
+                for x in &mut bytes {
+                    *x = self.int()?;
+                }
+                FuncArgs::StrByte8(s, bytes)
+            },
+            FuncParams::StrFloat2 => FuncArgs::StrFloat2(self.str()?, self.floats()?),
+            FuncParams::StrInt => FuncArgs::StrInt(self.str()?, self.int()?),
+            FuncParams::StrIntFloatInt => FuncArgs::StrIntFloatInt(self.str()?, self.int()?, self.float()?, self.int()?),
+            FuncParams::StrIntFloat3Byte => FuncArgs::StrIntFloat3Byte(self.str()?, self.int()?, self.floats()?, self.int()?),
+            FuncParams::StrInt2 => FuncArgs::StrInt2(self.str()?, [self.int()?, self.int()?]),
+            FuncParams::StrText => FuncArgs::StrText(self.str()?, self.str()?),
+            FuncParams::Str2 => FuncArgs::Str2([self.str()?, self.str()?]),
+            FuncParams::Str2Int => FuncArgs::Str2Int([self.str()?, self.str()?], self.int()?),
+            FuncParams::Text => FuncArgs::Text(self.str()?),
+            FuncParams::TextVarListStrList => {
+                let text = self.str()?;
+                let mut vars = Vec::new();
+                while let Some((_, Token::Word(_))) = self.tokens.first() {
+                    vars.push(self.var()?);
+                }
+                let mut buttons = Vec::new();
+                while !self.tokens.is_empty() {
+                    buttons.push(self.str()?);
+                }
+                FuncArgs::TextVarListStrList(text, vars, buttons)
+            },
+            FuncParams::VarStr => FuncArgs::VarStr(self.var()?, self.str()?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Lexeme<'a> {
+    Str(&'a str),
+    Word(&'a str),
+    Number(&'a str),
+    Op(&'static str),
+    Open,
+    Close,
+    Arrow,
+}
+
+fn lex_expr(expr: &str) -> Result<Vec<Lexeme<'_>>, String> {
+    let mut lexemes = Vec::new();
+    let mut rest = expr;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() { break; }
+        let (lexeme, len) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or_else(|| "unterminated string".to_string())?;
+            (Lexeme::Str(&quoted[.. end]), end + 2)
+        } else if rest.starts_with("->") {
+            (Lexeme::Arrow, 2)
+        } else if rest.starts_with('(') {
+            (Lexeme::Open, 1)
+        } else if rest.starts_with(')') {
+            (Lexeme::Close, 1)
+        } else if let Some(&(op, _)) = EXPR_OPS.iter().filter(|x| rest.starts_with(x.0)).max_by_key(|x| x.0.len()) {
+            (Lexeme::Op(op), op.len())
+        } else {
+            let len = rest.find(|c: char| c.is_whitespace() || ",\"()+-*/=!<>".contains(c)).unwrap_or(rest.len());
+            if len == 0 {
+                return Err(format!("unexpected '{}'", &rest[.. rest.chars().next().unwrap().len_utf8()]));
+            }
+            let word = &rest[.. len];
+            let bytes = word.as_bytes();
+            if bytes[0].is_ascii_digit() || bytes[0] == b'.' && bytes.get(1).is_some_and(u8::is_ascii_digit) {
+                if !bytes.iter().all(|&x| x.is_ascii_digit() || x == b'.') || word.parse::<f32>().is_err() {
+                    return Err(format!("invalid number '{word}'"));
+                }
+                (Lexeme::Number(word), len)
+            } else {
+                (Lexeme::Word(word), len)
+            }
+        };
+        lexemes.push(lexeme);
+        rest = &rest[len ..];
+    }
+    Ok(lexemes)
+}
+
+enum Expr {
+    Token(ExprToken),
+    Neg(Box<Expr>),
+    Paren(Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+}
+
+impl Expr {
+    fn infix(self, tokens: &mut Vec<ExprToken>) {
+        match self {
+            Expr::Token(token) => tokens.push(token),
+            Expr::Neg(expr) => {
+                tokens.push(ExprToken::Text("-".into()));
+                expr.infix(tokens);
+            },
+            Expr::Paren(expr) => {
+                tokens.push(ExprToken::Text("(".into()));
+                expr.infix(tokens);
+                tokens.push(ExprToken::Text(")".into()));
+            },
+            Expr::Binary(left, op, right) => {
+                left.infix(tokens);
+                tokens.push(ExprToken::Text(op.into()));
+                right.infix(tokens);
+            },
+        }
+    }
+
+    fn postfix(self, tokens: &mut Vec<ExprToken>) {
+        match self {
+            Expr::Token(token) => tokens.push(token),
+            Expr::Neg(expr) => {
+                tokens.push(ExprToken::Text("0".into()));
+                expr.postfix(tokens);
+                tokens.push(ExprToken::Text("-".into()));
+            },
+            Expr::Paren(expr) => expr.postfix(tokens),
+            Expr::Binary(left, op, right) => {
+                left.postfix(tokens);
+                right.postfix(tokens);
+                tokens.push(ExprToken::Text(op.into()));
+            },
+        }
+    }
+}
+
+struct ExprParser<'a, 'b> {
+    lexemes: &'b [Lexeme<'a>],
+    vars: &'b Vars<'b>,
+}
+
+impl<'a, 'b> ExprParser<'a, 'b> {
+    fn peek(&self) -> Option<Lexeme<'a>> { self.lexemes.first().copied() }
+
+    fn next(&mut self) -> Result<Lexeme<'a>, String> {
+        let (&first, rest) = self.lexemes.split_first().ok_or_else(|| "unexpected end of expression".to_string())?;
+        self.lexemes = rest;
+        Ok(first)
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut left = self.operand()?;
+        while let Some(Lexeme::Op(op)) = self.peek() {
+            let precedence = expr_op_precedence(op).unwrap();
+            if precedence < min_precedence { break; }
+            self.next()?;
+            let right = self.expr(precedence + 1)?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expr, String> {
+        let token = match self.next()? {
+            Lexeme::Open => {
+                let expr = self.expr(0)?;
+                if self.next()? != Lexeme::Close {
+                    return Err("')' expected".into());
+                }
+                return Ok(Expr::Paren(Box::new(expr)));
+            },
+            Lexeme::Op("-") => {
+                if let Some(Lexeme::Number(number)) = self.peek() {
+                    self.next()?;
+                    return Ok(Expr::Token(ExprToken::Text(format!("-{number}"))));
+                }
+                return Ok(Expr::Neg(Box::new(self.operand()?)));
+            },
+            Lexeme::Number(number) => ExprToken::Text(number.into()),
+            Lexeme::Str(reference) | Lexeme::Word(reference) if self.peek() == Some(Lexeme::Arrow) => {
+                self.next()?;
+                let Lexeme::Word(name) = self.next()? else { return Err("function name expected".into()); };
+                self.call(Some(reference), name)?
+            },
+            Lexeme::Str(owner) => match self.next()? {
+                Lexeme::Word(name) if name.starts_with('.') => ExprToken::Var(self.vars.remote(owner, &name[1 ..])?),
+                _ => return Err(format!("unexpected string '{owner}'")),
+            },
+            Lexeme::Word(word) => {
+                if self.vars.find(word).is_none() && !word.contains('.') && FUNCS.contains_key(&word.to_lowercase()) {
+                    self.call(None, word)?
+                } else {
+                    ExprToken::Var(self.vars.var(word)?)
+                }
+            },
+            _ => return Err("operand expected".into()),
+        };
+        Ok(Expr::Token(token))
+    }
+
+    fn call(&mut self, reference: Option<&str>, name: &str) -> Result<ExprToken, String> {
+        let func = *FUNCS.get(&name.to_lowercase()).ok_or_else(|| format!("unknown function '{name}'"))?;
+        let tokens = self.lexemes.iter().map_while(|x| match *x {
+            Lexeme::Str(s) => Some((0, Token::Str(s))),
+            Lexeme::Word(s) | Lexeme::Number(s) => Some((0, Token::Word(s))),
+            _ => None
+        }).collect::<Vec<_>>();
+        let mut args = Args { tokens: &tokens, vars: self.vars };
+        let func_args = args.args(func.params())?;
+        self.lexemes = &self.lexemes[tokens.len() - args.tokens.len() ..];
+        Ok(ExprToken::Func(reference.map(|x| x.to_string()), Stmt { func, args: func_args }))
+    }
+}
+
+fn keyword(tokens: &[(usize, Token)]) -> Option<String> {
+    match tokens.first() {
+        Some((_, Token::Word(word))) => Some(word.to_lowercase()),
+        _ => None
+    }
+}
+
+fn rest<'a>(line: &'a str, tokens: &[(usize, Token)], index: usize) -> &'a str {
+    tokens.get(index).map_or("", |&(start, _)| line[start ..].trim_end())
+}
+
+struct Compiler<'a, 'r> {
+    code_page: CodePage,
+    vars: Vars<'r>,
+    name: Option<&'a str>,
+    stmts: Vec<Stmt>,
+    branches: Vec<Vec<usize>>,
+    ended: bool,
+}
+
+impl<'a, 'r> Compiler<'a, 'r> {
+    fn declaration(&mut self, keyword: &str, tokens: &[(usize, Token<'a>)]) -> Result<bool, String> {
+        let var_type = match keyword {
+            "short" => 0,
+            "long" => 1,
+            "float" => 2,
+            "begin" => {
+                let [_, (_, Token::Str(name) | Token::Word(name))] = tokens else {
+                    return Err("script name expected".into());
+                };
+                if self.name.replace(name).is_some() {
+                    return Err("duplicate 'Begin'".into());
+                }
+                return Ok(true);
+            },
+            _ => return Ok(false)
+        };
+        let [_, (_, Token::Word(name))] = tokens else {
+            return Err("variable name expected".into());
+        };
+        if self.vars.find(name).is_some() {
+            return Err(format!("duplicate variable '{name}'"));
+        }
+        if self.vars.globals.borrow().iter().any(|x| x.eq_ignore_ascii_case(name)) {
+            return Err(format!("variable '{name}' used before declaration"));
+        }
+        self.vars.names[var_type].push(name.to_string());
+        Ok(true)
+    }
+
+    fn expr(&self, expr: &str, postfix: bool) -> Result<String, String> {
+        let lexemes = lex_expr(expr)?;
+        if lexemes.is_empty() {
+            return Err("expression expected".into());
+        }
+        let mut parser = ExprParser { lexemes: &lexemes, vars: &self.vars };
+        let expr = parser.expr(0)?;
+        if !parser.lexemes.is_empty() {
+            return Err("unexpected token after expression".into());
+        }
+        let mut tokens = Vec::new();
+        if postfix { expr.postfix(&mut tokens); } else { expr.infix(&mut tokens); }
+        write_expr(self.code_page, &tokens)
+    }
+
+    fn branch(&mut self, func: Func, args: FuncArgs) {
+        if let Some(branches) = self.branches.last_mut() {
+            branches.push(self.stmts.len());
+        }
+        self.stmts.push(Stmt { func, args });
+    }
+
+    fn end_if(&mut self) -> Result<(), String> {
+        let mut branches = self.branches.pop().ok_or_else(|| "'endif' without 'if'".to_string())?;
+        branches.push(self.stmts.len());
+        for pair in branches.windows(2) {
+            let jump = (pair[1] - pair[0] - 1).try_into().map_err(|_| "too long 'if' block".to_string())?;
+            match &mut self.stmts[pair[0]].args {
+                FuncArgs::Byte(x) | FuncArgs::ByteStr(x, _) => *x = jump,
+                _ => unreachable!()
+            }
+        }
+        self.stmts.push(Stmt { func: Func::EndIf, args: FuncArgs::None });
+        Ok(())
+    }
+
+    fn line(&mut self, line: &'a str) -> Result<(), String> {
+        if self.ended {
+            return Err("statement after 'End'".into());
+        }
+        let tokens = tokenize(line)?;
+        let keyword = keyword(&tokens).unwrap_or_default();
+        if self.name.is_none() && keyword != "begin" {
+            return Err("'Begin' expected".into());
+        }
+        if self.declaration(&keyword, &tokens)? { return Ok(()); }
+        match keyword.as_str() {
+            "if" => {
+                let expr = self.expr(rest(line, &tokens, 1), false)?;
+                self.branches.push(Vec::new());
+                self.branch(Func::If, FuncArgs::ByteStr(0, expr));
+            },
+            "elseif" | "else" if self.branches.is_empty() => return Err(format!("'{keyword}' without 'if'")),
+            "elseif" => {
+                let expr = self.expr(rest(line, &tokens, 1), false)?;
+                self.branch(Func::ElseIf, FuncArgs::ByteStr(0, expr));
+            },
+            "else" => self.branch(Func::Else, FuncArgs::Byte(0)),
+            "endif" => self.end_if()?,
+            "return" => self.stmts.push(Stmt { func: Func::Return, args: FuncArgs::None }),
+            "end" => {
+                if !self.branches.is_empty() {
+                    return Err("'End' inside 'if' block".into());
+                }
+                self.stmts.push(Stmt { func: Func::End, args: FuncArgs::None });
+                self.ended = true;
+            },
+            "set" => {
+                let to = tokens.iter().position(|(_, x)| matches!(x, Token::Word(w) if w.eq_ignore_ascii_case("to")))
+                    .ok_or_else(|| "'to' expected".to_string())?;
+                let mut args = Args { tokens: &tokens[1 .. to], vars: &self.vars };
+                let var = args.var()?;
+                args.end()?;
+                let expr = self.expr(rest(line, &tokens, to + 1), true)?;
+                self.stmts.push(Stmt { func: Func::Set, args: FuncArgs::VarStr(var, expr) });
+            },
+            _ => {
+                let mut tokens = &tokens[..];
+                if let [(_, Token::Str(reference) | Token::Word(reference)), (_, Token::Arrow), rest @ ..] = tokens {
+                    self.stmts.push(Stmt { func: Func::SetRef, args: FuncArgs::Str(reference.to_string()) });
+                    tokens = rest;
+                }
+                let Some((_, Token::Word(name))) = tokens.first() else {
+                    return Err("function name expected".into());
+                };
+                let func = *FUNCS.get(&name.to_lowercase()).ok_or_else(|| format!("unknown function '{name}'"))?;
+                let args = Args { tokens: &tokens[1 ..], vars: &self.vars }.func_args(func.params())?;
+                self.stmts.push(Stmt { func, args });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ScriptData {
+    pub fn compile(code_page: CodePage, source: &str) -> Result<CompiledScript, CompileError> {
+        ScriptData::compile_with(code_page, source, &|_, _| None)
+    }
+
+    pub fn compile_with(
+        code_page: CodePage, source: &str, remote_vars: &dyn Fn(&str, &str) -> Option<(VarType, u16)>
+    ) -> Result<CompiledScript, CompileError> {
+        let mut compiler = Compiler {
+            code_page,
+            vars: Vars { names: [Vec::new(), Vec::new(), Vec::new()], globals: RefCell::new(Vec::new()), remote_vars },
+            name: None,
+            stmts: Vec::new(),
+            branches: Vec::new(),
+            ended: false,
+        };
+        for (index, line) in source.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() { continue; }
+            compiler.line(line).map_err(|message| CompileError { line: index + 1, message })?;
+        }
+        let last_line = source.lines().count();
+        let name = compiler.name.ok_or_else(|| CompileError { line: 1, message: "'Begin' expected".into() })?;
+        if !compiler.ended {
+            return Err(CompileError { line: last_line, message: "'End' expected".into() });
+        }
+        let data = ScriptData { stmts: compiler.stmts, raw: Vec::new() };
+        let data_size = data.to_bytes(code_page, false)
+            .map_err(|message| CompileError { line: last_line, message })?
+            .len();
+        let mut var_table_size = 0;
+        for name in compiler.vars.names.iter().flatten() {
+            let bytes = code_page.encode(name).map_err(|_| CompileError {
+                line: last_line, message: format!("the '{name}' variable name is not representable in {code_page:?} code page")
+            })?;
+            var_table_size += bytes.len() + 1;
+        }
+        let [shorts, longs, floats] = &compiler.vars.names;
+        let metadata = ScriptMetadata {
+            name: name.to_string(),
+            vars: ScriptVars { shorts: shorts.len() as u32, longs: longs.len() as u32, floats: floats.len() as u32 },
+            data_size: data_size as u32,
+            var_table_size: var_table_size as u32,
+        };
+        let [shorts, longs, floats] = compiler.vars.names;
+        let vars = StringZList { vec: shorts.into_iter().chain(longs).chain(floats).collect(), has_tail_zero: true };
+        Ok(CompiledScript { metadata, vars, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::script_data::*;
+
+    #[test]
+    fn compile_decompiled_script() {
+        let source = "\
+Begin TestScript
+short done
+float timer
+if ( done == 0 )
+    \"player\"->AddItem \"gold_001\" 100
+    FadeOut timer
+    set done to 1
+else
+    MessageBox \"Done %g\" timer \"Ok\"
+endif
+End
+";
+        let compiled = ScriptData::compile(CodePage::English, source).unwrap();
+        assert_eq!(compiled.metadata.name, "TestScript");
+        assert_eq!(compiled.metadata.vars, ScriptVars { shorts: 1, longs: 0, floats: 1 });
+        assert_eq!(compiled.metadata.var_table_size, 11);
+        assert_eq!(compiled.vars.vec, vec!["done".to_string(), "timer".to_string()]);
+        assert_eq!(
+            compiled.metadata.data_size as usize,
+            compiled.data.to_bytes(CodePage::English, false).unwrap().len()
+        );
+        assert_eq!(compiled.data.stmts[0], Stmt { func: Func::If, args: FuncArgs::ByteStr(4, " ( s\x01\x00 == 0 )".into()) });
+        assert_eq!(compiled.data.stmts[5], Stmt { func: Func::Else, args: FuncArgs::Byte(1) });
+        assert_eq!(compiled.data.decompile(CodePage::English, &compiled.metadata, &compiled.vars.vec).unwrap(), source);
+    }
+
+    #[test]
+    fn compile_expressions() {
+        let source = "\
+Begin TestScript
+short done
+float timer
+if ( OnActivate == 1 )
+    set timer to ( timer + 1 ) * -2
+    \"player\"->AddItem \"gold_001\" 100
+elseif ( done != 0 )
+    set done to 1
+endif
+End
+";
+        let compiled = ScriptData::compile(CodePage::English, source).unwrap();
+        let bytes: &[u8] = &[
+            b"\x06\x01\x03\x0D ( X\x18\x10 == 1 )" as &[u8],
+            b"\x05\x01f\x01\x00\x0D f\x01\x00 1 + -2 *",
+            b"\x0C\x01\x06player",
+            b"\xD4\x10\x08gold_001\x64\x00",
+            b"\x08\x01\x01\x0D ( s\x01\x00 != 0 )",
+            b"\x05\x01s\x01\x00\x02 1",
+            b"\x09\x01",
+            b"\x01\x01",
+        ].concat();
+        assert_eq!(compiled.data.to_bytes(CodePage::English, false).unwrap(), bytes);
+        assert_eq!(ScriptData::from_bytes(CodePage::English, bytes), compiled.data);
+        assert_eq!(compiled.data.decompile(CodePage::English, &compiled.metadata, &compiled.vars.vec).unwrap(), source);
+        let remote = ScriptData::compile_with(
+            CodePage::English,
+            "Begin X\nset \"guard\".alarm to guard.alarm - timer\nEnd",
+            &|owner, var| (owner == "guard" && var == "alarm").then_some((VarType::Short, 2))
+        ).unwrap();
+        assert_eq!(remote.data.stmts[0], Stmt {
+            func: Func::Set,
+            args: FuncArgs::VarStr(
+                Var::Local { owner: Some("guard".into()), var_type: VarType::Short, index: 2 },
+                " r\x05guards\x02\x00 G\x05timer -".into()
+            )
+        });
+    }
+
+    #[test]
+    fn compile_error_line() {
+        let error = ScriptData::compile(CodePage::English, "Begin X\nshort a\nUnknownFunc 1\nEnd\n").unwrap_err();
+        assert_eq!(error, CompileError { line: 3, message: "unknown function 'UnknownFunc'".into() });
+        let error = ScriptData::compile(CodePage::English, "Begin X\nset a to 1\nshort a\nEnd\n").unwrap_err();
+        assert_eq!(error, CompileError { line: 3, message: "variable 'a' used before declaration".into() });
+        let error = ScriptData::compile(CodePage::English, "short a\nBegin X\nEnd\n").unwrap_err();
+        assert_eq!(error, CompileError { line: 1, message: "'Begin' expected".into() });
+        let error = ScriptData::compile(CodePage::English, "Begin X\nset a to guard.alarm\nEnd\n").unwrap_err();
+        assert_eq!(error, CompileError { line: 2, message: "unknown remote variable 'guard.alarm'".into() });
+    }
+}
@@ -0,0 +1,343 @@
+use super::*;
+use super::expr::*;
+use crate::field::ScriptMetadata;
+use std::error::Error;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DecompileError {
+    MissingVarName { var_type: VarType, index: u16 },
+    UnknownLocal { stmt_index: usize, var_type: VarType, index: u16 },
+    RemoteLocal { stmt_index: usize, owner: String, var_type: VarType, index: u16 },
+    InvalidExpression { stmt_index: usize },
+}
+
+impl fmt::Display for DecompileError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DecompileError::MissingVarName { var_type, index } =>
+                write!(f, "missing name of {var_type} local variable #{index}"),
+            DecompileError::UnknownLocal { stmt_index, var_type, index } =>
+                write!(f, "undeclared {var_type} local variable #{index} in statement {stmt_index}"),
+            DecompileError::RemoteLocal { stmt_index, owner, var_type, index } =>
+                write!(f, "unknown {var_type} local variable #{index} of '{owner}' in statement {stmt_index}"),
+            DecompileError::InvalidExpression { stmt_index } => write!(f, "invalid expression in statement {stmt_index}"),
+        }
+    }
+}
+
+impl Error for DecompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { None }
+}
+
+struct Decompiler<'a> {
+    code_page: CodePage,
+    metadata: &'a ScriptMetadata,
+    var_names: &'a [String],
+    remote_vars: &'a dyn Fn(&str, VarType, u16) -> Option<String>,
+    res: String,
+    indent: usize,
+    reference: Option<&'a str>,
+    stmt_index: usize,
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{s}\"")
+}
+
+impl<'a> Decompiler<'a> {
+    fn local_name(&self, var_type: VarType, index: u16) -> Option<&'a String> {
+        local_var_slot(&self.metadata.vars, var_type, index).and_then(|slot| self.var_names.get(slot))
+    }
+
+    fn local(&self, var_type: VarType, index: u16) -> Result<String, DecompileError> {
+        self.local_name(var_type, index).cloned()
+            .ok_or(DecompileError::UnknownLocal { stmt_index: self.stmt_index, var_type, index })
+    }
+
+    fn var(&self, var: &Var) -> Result<String, DecompileError> {
+        match var {
+            &Var::Local { owner: None, var_type, index } => self.local(var_type, index),
+            &Var::Local { owner: Some(ref owner), var_type, index } => {
+                let name = (self.remote_vars)(owner, var_type, index).ok_or_else(|| DecompileError::RemoteLocal {
+                    stmt_index: self.stmt_index, owner: owner.clone(), var_type, index
+                })?;
+                Ok(format!("{}.{name}", quote(owner)))
+            },
+            Var::Global { name } => Ok(name.clone()),
+        }
+    }
+
+    fn float(&self, float: &Float) -> Result<String, DecompileError> {
+        match float {
+            &Float::Val { val } => Ok(format!("{val}")),
+            &Float::Var { var_type, index } => self.local(var_type, index),
+        }
+    }
+
+    fn floats(&self, floats: &[Float]) -> Result<Vec<String>, DecompileError> {
+        floats.iter().map(|x| self.float(x)).collect()
+    }
+
+    fn args(&self, args: &FuncArgs) -> Result<Vec<String>, DecompileError> {
+        Ok(match args {
+            FuncArgs::None => Vec::new(),
+            FuncArgs::Byte(a1) => vec![a1.to_string()],
+            FuncArgs::ByteStr(a1, a2) => vec![a1.to_string(), quote(a2)],
+            FuncArgs::CharFloat(a1, a2) => vec![a1.clone(), self.float(a2)?],
+            FuncArgs::Float(a1) => vec![self.float(a1)?],
+            FuncArgs::FloatStr(a1, a2) => vec![self.float(a1)?, quote(a2)],
+            FuncArgs::Float3Byte(a1, a2) => [self.floats(a1)?, vec![a2.to_string()]].concat(),
+            FuncArgs::Float3IntListByte(a1, a2, a3) =>
+                [self.floats(a1)?, a2.iter().map(|x| x.to_string()).collect(), vec![a3.to_string()]].concat(),
+            FuncArgs::Float4(a1) => self.floats(a1)?,
+            FuncArgs::Float4Str(a1, a2) => [self.floats(a1)?, vec![quote(a2)]].concat(),
+            FuncArgs::Int(a1) => vec![a1.to_string()],
+            FuncArgs::IntByte(a1, a2) => vec![a1.to_string(), a2.to_string()],
+            FuncArgs::Int2(a1) => a1.iter().map(|x| x.to_string()).collect(),
+            FuncArgs::Str(a1) => vec![quote(a1)],
+            FuncArgs::StrByte(a1, a2) => vec![quote(a1), a2.to_string()],
+            FuncArgs::StrByte8(a1, a2) => [quote(a1)].into_iter().chain(a2.iter().map(|x| x.to_string())).collect(),
+            FuncArgs::StrFloat2(a1, a2) => [vec![quote(a1)], self.floats(a2)?].concat(),
+            FuncArgs::StrInt(a1, a2) => vec![quote(a1), a2.to_string()],
+            FuncArgs::StrIntFloatInt(a1, a2, a3, a4) => vec![quote(a1), a2.to_string(), self.float(a3)?, a4.to_string()],
+            FuncArgs::StrIntFloat3Byte(a1, a2, a3, a4) =>
+                [vec![quote(a1), a2.to_string()], self.floats(a3)?, vec![a4.to_string()]].concat(),
+            FuncArgs::StrInt2(a1, a2) => [quote(a1)].into_iter().chain(a2.iter().map(|x| x.to_string())).collect(),
+            FuncArgs::StrText(a1, a2) => vec![quote(a1), quote(a2)],
+            FuncArgs::Str2(a1) => a1.iter().map(|x| quote(x)).collect(),
+            FuncArgs::Str2Int(a1, a2) => a1.iter().map(|x| quote(x)).chain([a2.to_string()]).collect(),
+            FuncArgs::Text(a1) => vec![quote(a1)],
+            FuncArgs::TextVarListStrList(a1, a2, a3) => [
+                vec![quote(a1)],
+                a2.iter().map(|x| self.var(x)).collect::<Result<_, _>>()?,
+                a3.iter().map(|x| quote(x)).collect()
+            ].concat(),
+            FuncArgs::VarStr(a1, a2) => vec![self.var(a1)?, a2.clone()],
+        })
+    }
+
+    fn call(&self, func: Func, args: &FuncArgs) -> Result<String, DecompileError> {
+        let mut call = func.to_string();
+        for arg in self.args(args)? {
+            call.push(' ');
+            call.push_str(&arg);
+        }
+        Ok(call)
+    }
+
+    fn token(&self, token: &ExprToken) -> Result<String, DecompileError> {
+        match token {
+            ExprToken::Var(var) => self.var(var),
+            ExprToken::Func(None, stmt) => self.call(stmt.func, &stmt.args),
+            ExprToken::Func(Some(reference), stmt) => Ok(format!("{}->{}", quote(reference), self.call(stmt.func, &stmt.args)?)),
+            ExprToken::Text(text) => Ok(text.clone()),
+        }
+    }
+
+    fn expr(&self, expr: &str) -> Result<Vec<ExprToken>, DecompileError> {
+        read_expr(self.code_page, expr).ok_or(DecompileError::InvalidExpression { stmt_index: self.stmt_index })
+    }
+
+    fn infix(&self, expr: &str) -> Result<String, DecompileError> {
+        let tokens = self.expr(expr)?.iter().map(|x| self.token(x)).collect::<Result<Vec<_>, _>>()?;
+        Ok(tokens.join(" "))
+    }
+
+    fn postfix(&self, expr: &str) -> Result<String, DecompileError> {
+        let invalid = DecompileError::InvalidExpression { stmt_index: self.stmt_index };
+        let mut stack = Vec::new();
+        for token in self.expr(expr)? {
+            let op = match &token {
+                ExprToken::Text(text) if text == "(" || text == ")" => return Err(invalid),
+                ExprToken::Text(text) => expr_op_precedence(text).map(|x| (text, x)),
+                _ => None
+            };
+            let Some((op, precedence)) = op else {
+                stack.push((self.token(&token)?, u8::MAX));
+                continue;
+            };
+            let (Some(right), Some(left)) = (stack.pop(), stack.pop()) else { return Err(invalid); };
+            let left = if left.1 < precedence { format!("( {} )", left.0) } else { left.0 };
+            let right = if right.1 <= precedence { format!("( {} )", right.0) } else { right.0 };
+            stack.push((format!("{left} {op} {right}"), precedence));
+        }
+        let Some((expr, _)) = stack.pop().filter(|_| stack.is_empty()) else { return Err(invalid); };
+        Ok(expr)
+    }
+
+    fn line(&mut self, line: &str) {
+        for _ in 0 .. self.indent {
+            self.res.push_str("    ");
+        }
+        if let Some(reference) = self.reference.take() {
+            write!(self.res, "{}->", quote(reference)).unwrap();
+        }
+        self.res.push_str(line);
+        self.res.push('\n');
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) -> Result<(), DecompileError> {
+        match (stmt.func, &stmt.args) {
+            (Func::If, FuncArgs::ByteStr(_, expr)) => {
+                let expr = self.infix(expr)?;
+                self.line(&format!("if {expr}"));
+                self.indent += 1;
+            },
+            (Func::ElseIf, FuncArgs::ByteStr(_, expr)) => {
+                let expr = self.infix(expr)?;
+                self.indent = self.indent.saturating_sub(1);
+                self.line(&format!("elseif {expr}"));
+                self.indent += 1;
+            },
+            (Func::Else, _) => {
+                self.indent = self.indent.saturating_sub(1);
+                self.line("else");
+                self.indent += 1;
+            },
+            (Func::EndIf, _) => {
+                self.indent = self.indent.saturating_sub(1);
+                self.line("endif");
+            },
+            (Func::Set, FuncArgs::VarStr(var, expr)) => {
+                let var = self.var(var)?;
+                let expr = self.postfix(expr)?;
+                self.line(&format!("set {var} to {expr}"));
+            },
+            (Func::SetRef, FuncArgs::Str(reference)) => {
+                self.reference = Some(reference);
+            },
+            (Func::End, _) => {
+                self.indent = 0;
+                self.line("End");
+            },
+            (Func::Return, _) => self.line("return"),
+            (func, args) => {
+                let line = self.call(func, args)?;
+                self.line(&line);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ScriptData {
+    pub fn decompile(
+        &self, code_page: CodePage, metadata: &ScriptMetadata, var_names: &[String]
+    ) -> Result<String, DecompileError> {
+        self.decompile_with(code_page, metadata, var_names, &|_, _, _| None)
+    }
+
+    pub fn decompile_with(
+        &self, code_page: CodePage, metadata: &ScriptMetadata, var_names: &[String],
+        remote_vars: &dyn Fn(&str, VarType, u16) -> Option<String>
+    ) -> Result<String, DecompileError> {
+        let mut decompiler = Decompiler {
+            code_page, metadata, var_names, remote_vars, res: String::new(), indent: 0, reference: None, stmt_index: 0
+        };
+        writeln!(decompiler.res, "Begin {}", metadata.name).unwrap();
+        for (var_type, count) in [
+            (VarType::Short, metadata.vars.shorts), (VarType::Long, metadata.vars.longs), (VarType::Float, metadata.vars.floats)
+        ] {
+            for index in 1 ..= count.min(u16::MAX as u32) as u16 {
+                let name = decompiler.local_name(var_type, index).ok_or(DecompileError::MissingVarName { var_type, index })?;
+                writeln!(decompiler.res, "{} {name}", var_type.to_string().to_lowercase()).unwrap();
+            }
+        }
+        for (stmt_index, stmt) in self.stmts.iter().enumerate() {
+            decompiler.stmt_index = stmt_index;
+            decompiler.stmt(stmt)?;
+        }
+        if !self.raw.is_empty() {
+            writeln!(decompiler.res, "; {} bytes of unknown bytecode", self.raw.len()).unwrap();
+        }
+        Ok(decompiler.res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::script_data::*;
+
+    #[test]
+    fn decompile_script() {
+        let metadata = ScriptMetadata {
+            name: "TestScript".into(),
+            vars: ScriptVars { shorts: 1, longs: 0, floats: 1 },
+            data_size: 0,
+            var_table_size: 0
+        };
+        let var_names = vec!["done".to_string(), "timer".to_string()];
+        let data = ScriptData {
+            stmts: vec![
+                Stmt { func: Func::If, args: FuncArgs::ByteStr(3, " ( s\x01\x00 == 0 )".into()) },
+                Stmt { func: Func::SetRef, args: FuncArgs::Str("player".into()) },
+                Stmt { func: Func::AddItem, args: FuncArgs::StrInt("gold_001".into(), 100) },
+                Stmt { func: Func::FadeOut, args: FuncArgs::Float(Float::Var { var_type: VarType::Float, index: 1 }) },
+                Stmt {
+                    func: Func::Set,
+                    args: FuncArgs::VarStr(Var::Local { owner: None, var_type: VarType::Short, index: 1 }, " 1".into())
+                },
+                Stmt { func: Func::EndIf, args: FuncArgs::None },
+                Stmt { func: Func::End, args: FuncArgs::None },
+            ],
+            raw: Vec::new()
+        };
+        assert_eq!(data.decompile(CodePage::English, &metadata, &var_names).unwrap(), "\
+Begin TestScript
+short done
+float timer
+if ( done == 0 )
+    \"player\"->AddItem \"gold_001\" 100
+    FadeOut timer
+    set done to 1
+endif
+End
+");
+    }
+    #[test]
+    fn decompile_expressions() {
+        let metadata = ScriptMetadata {
+            name: "TestScript".into(),
+            vars: ScriptVars { shorts: 1, longs: 0, floats: 1 },
+            data_size: 0,
+            var_table_size: 0
+        };
+        let var_names = vec!["done".to_string(), "timer".to_string()];
+        let bytes: &[u8] = &[
+            b"\x06\x01\x01\x0D ( X\x18\x10 == 1 )" as &[u8],
+            b"\x05\x01f\x01\x00\x15 f\x01\x00 r\x05guards\x02\x00 1 - /",
+            b"\x09\x01",
+            b"\x01\x01",
+        ].concat();
+        let data = ScriptData::from_bytes(CodePage::English, bytes);
+        assert!(data.raw.is_empty());
+        let remote_vars = |owner: &str, var_type, index| {
+            (owner == "guard" && var_type == VarType::Short && index == 2).then(|| "alarm".to_string())
+        };
+        assert_eq!(data.decompile_with(CodePage::English, &metadata, &var_names, &remote_vars).unwrap(), "\
+Begin TestScript
+short done
+float timer
+if ( OnActivate == 1 )
+    set timer to timer / ( \"guard\".alarm - 1 )
+endif
+End
+");
+        assert_eq!(
+            data.decompile(CodePage::English, &metadata, &var_names),
+            Err(DecompileError::RemoteLocal { stmt_index: 1, owner: "guard".into(), var_type: VarType::Short, index: 2 })
+        );
+        assert_eq!(
+            data.decompile_with(CodePage::English, &metadata, &var_names[.. 1], &remote_vars),
+            Err(DecompileError::MissingVarName { var_type: VarType::Float, index: 1 })
+        );
+        let mut invalid = data.clone();
+        invalid.stmts[0].args = FuncArgs::ByteStr(1, "( done == 0 )".into());
+        assert_eq!(
+            invalid.decompile(CodePage::English, &metadata, &var_names),
+            Err(DecompileError::InvalidExpression { stmt_index: 0 })
+        );
+    }
+}
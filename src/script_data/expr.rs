@@ -0,0 +1,102 @@
+use super::*;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum ExprToken {
+    Var(Var),
+    Func(Option<String>, Stmt),
+    Text(String),
+}
+
+pub(crate) const EXPR_OPS: [(&str, u8); 10] = [
+    ("==", 0), ("!=", 0), ("<", 0), ("<=", 0), (">", 0), (">=", 0),
+    ("+", 1), ("-", 1),
+    ("*", 2), ("/", 2),
+];
+
+pub(crate) fn expr_op_precedence(op: &str) -> Option<u8> {
+    EXPR_OPS.iter().find(|x| x.0 == op).map(|x| x.1)
+}
+
+fn is_expr_text(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|x| x.is_ascii_digit() || b".-+*/=!<>()".contains(&x))
+}
+
+pub(crate) fn write_expr(code_page: CodePage, tokens: &[ExprToken]) -> Result<String, String> {
+    let mut res = Vec::new();
+    for token in tokens {
+        res.push(b' ');
+        match token {
+            ExprToken::Var(var) => var.write(code_page, &mut res)?,
+            ExprToken::Func(reference, stmt) => {
+                if let Some(reference) = reference {
+                    res.push(b'Z');
+                    write_str(code_page, reference, &mut res)?;
+                } else {
+                    res.push(b'X');
+                }
+                stmt.write(code_page, &mut res)?;
+            },
+            ExprToken::Text(text) => res.extend_from_slice(text.as_bytes()),
+        }
+    }
+    if res.len() > u8::MAX as usize {
+        return Err("too long expression".into());
+    }
+    Ok(code_page.decode(&res))
+}
+
+pub(crate) fn read_expr(code_page: CodePage, expr: &str) -> Option<Vec<ExprToken>> {
+    let bytes = code_page.encode(expr).ok()?;
+    let mut input = &bytes[..];
+    let mut tokens = Vec::new();
+    while let Some(token) = input.strip_prefix(b" ") {
+        let (rest, token) = match token.first()? {
+            b's' | b'l' | b'f' | b'r' | b'G' => {
+                let (rest, var) = parser::var(code_page)(token).ok()?;
+                (rest, ExprToken::Var(var))
+            },
+            b'X' => {
+                let (rest, stmt) = parser::stmt(code_page)(&token[1 ..]).ok()?;
+                (rest, ExprToken::Func(None, stmt))
+            },
+            b'Z' => {
+                let (rest, reference) = parser::string(code_page)(&token[1 ..]).ok()?;
+                let (rest, stmt) = parser::stmt(code_page)(rest).ok()?;
+                (rest, ExprToken::Func(Some(reference), stmt))
+            },
+            _ => {
+                let len = token.iter().position(|&x| x == b' ').unwrap_or(token.len());
+                let text = std::str::from_utf8(&token[.. len]).ok().filter(|x| is_expr_text(x))?;
+                (&token[len ..], ExprToken::Text(text.into()))
+            },
+        };
+        tokens.push(token);
+        input = rest;
+    }
+    if !input.is_empty() { return None; }
+    Some(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::script_data::expr::*;
+
+    #[test]
+    fn expr_round_trip() {
+        let tokens = vec![
+            ExprToken::Text("(".into()),
+            ExprToken::Var(Var::Local { owner: None, var_type: VarType::Short, index: 32 }),
+            ExprToken::Text("==".into()),
+            ExprToken::Func(Some("player".into()), Stmt { func: Func::OnActivate, args: FuncArgs::None }),
+            ExprToken::Text(")".into()),
+        ];
+        let expr = write_expr(CodePage::English, &tokens).unwrap();
+        assert_eq!(
+            CodePage::English.encode(&expr).unwrap(),
+            b" ( s\x20\x00 == Z\x06player\x18\x10 )"
+        );
+        assert_eq!(read_expr(CodePage::English, &expr), Some(tokens));
+        assert_eq!(read_expr(CodePage::English, "( done == 0 )"), None);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::field::*;
+use crate::record::*;
+use crate::script_data::*;
+use crate::strings::*;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RecordViewError {
+    TagMismatch { expected: Tag, actual: Tag },
+    TagsMismatch { expected: &'static [Tag], actual: Tag },
+    UnexpectedField { record_tag: Tag, field_tag: Tag, field_index: usize },
+}
+
+impl Display for RecordViewError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RecordViewError::TagMismatch { expected, actual } =>
+                write!(f, "{expected} record expected, but {actual} record found"),
+            RecordViewError::TagsMismatch { expected, actual } => {
+                for (i, tag) in expected.iter().enumerate() {
+                    if i != 0 { write!(f, " or ")?; }
+                    write!(f, "{tag}")?;
+                }
+                write!(f, " record expected, but {actual} record found")
+            },
+            RecordViewError::UnexpectedField { record_tag, field_tag, field_index } =>
+                write!(f, "unexpected {field_tag} field value at index {field_index} in {record_tag} record"),
+        }
+    }
+}
+
+impl Error for RecordViewError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { None }
+}
+
+macro_rules! record_view {
+    (
+        $name:ident($record_tag:ident) $(until $($end_tag:ident),+)? {
+            $($field:ident, $field_mut:ident: $field_tag:ident => $variant:ident($ty:ty)),* $(,)?
+        }
+        [
+            $($list:ident, $list_mut:ident: $list_tag:ident => $list_variant:ident($list_ty:ty)),* $(,)?
+        ]
+    ) => {
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct $name {
+            record: Record,
+        }
+
+        impl $name {
+            fn head_len(record: &Record) -> usize {
+                #[allow(unused_mut)]
+                let mut len = record.fields.len();
+                $(
+                    if let Some(end) = record.fields.iter().position(|(tag, _)| $(*tag == $end_tag)||+) {
+                        len = end;
+                    }
+                )?
+                len
+            }
+
+            fn validate(record: &Record) -> Result<(), RecordViewError> {
+                if record.tag != $record_tag {
+                    return Err(RecordViewError::TagMismatch { expected: $record_tag, actual: record.tag });
+                }
+                let mut prev_tag = META;
+                for (field_index, (field_tag, field)) in record.fields[.. Self::head_len(record)].iter().enumerate() {
+                    if !FieldType::from_tags(record.tag, prev_tag, *field_tag, false).accepts(field) {
+                        return Err(RecordViewError::UnexpectedField { record_tag: record.tag, field_tag: *field_tag, field_index });
+                    }
+                    prev_tag = *field_tag;
+                }
+                Ok(())
+            }
+
+            pub fn record(&self) -> &Record { &self.record }
+
+            pub fn into_record(self) -> Record { self.record }
+
+            pub fn flags(&self) -> RecordFlags { self.record.flags }
+
+            pub fn flags_mut(&mut self) -> &mut RecordFlags { &mut self.record.flags }
+
+            fn head_fields(&self) -> &[(Tag, Field)] {
+                &self.record.fields[.. Self::head_len(&self.record)]
+            }
+
+            fn head_fields_mut(&mut self) -> &mut [(Tag, Field)] {
+                let len = Self::head_len(&self.record);
+                &mut self.record.fields[.. len]
+            }
+
+            $(
+                pub fn $field(&self) -> Option<&$ty> {
+                    self.head_fields().iter().find_map(|(tag, field)| match field {
+                        Field::$variant(v) if *tag == $field_tag => Some(v),
+                        _ => None
+                    })
+                }
+
+                pub fn $field_mut(&mut self) -> Option<&mut $ty> {
+                    self.head_fields_mut().iter_mut().find_map(|(tag, field)| match field {
+                        Field::$variant(v) if *tag == $field_tag => Some(v),
+                        _ => None
+                    })
+                }
+            )*
+
+            $(
+                pub fn $list(&self) -> impl Iterator<Item=&$list_ty> {
+                    self.head_fields().iter().filter_map(|(tag, field)| match field {
+                        Field::$list_variant(v) if *tag == $list_tag => Some(v),
+                        _ => None
+                    })
+                }
+
+                pub fn $list_mut(&mut self) -> impl Iterator<Item=&mut $list_ty> {
+                    self.head_fields_mut().iter_mut().filter_map(|(tag, field)| match field {
+                        Field::$list_variant(v) if *tag == $list_tag => Some(v),
+                        _ => None
+                    })
+                }
+            )*
+        }
+
+        impl TryFrom<Record> for $name {
+            type Error = (RecordViewError, Record);
+
+            fn try_from(record: Record) -> Result<Self, Self::Error> {
+                match Self::validate(&record) {
+                    Ok(()) => Ok($name { record }),
+                    Err(e) => Err((e, record))
+                }
+            }
+        }
+
+        impl TryFrom<&Record> for $name {
+            type Error = RecordViewError;
+
+            fn try_from(record: &Record) -> Result<Self, Self::Error> {
+                Self::validate(record)?;
+                Ok($name { record: record.clone() })
+            }
+        }
+
+        impl From<$name> for Record {
+            fn from(view: $name) -> Record { view.record }
+        }
+
+        impl AsRef<Record> for $name {
+            fn as_ref(&self) -> &Record { &self.record }
+        }
+    };
+}
+
+record_view!(NpcRecord(NPC_) {
+    id, id_mut: NAME => StringZ(StringZ),
+    name, name_mut: FNAM => StringZ(StringZ),
+    model, model_mut: MODL => StringZ(StringZ),
+    race, race_mut: RNAM => StringZ(StringZ),
+    class, class_mut: CNAM => StringZ(StringZ),
+    faction, faction_mut: ANAM => StringZ(StringZ),
+    head, head_mut: BNAM => StringZ(StringZ),
+    hair, hair_mut: KNAM => StringZ(StringZ),
+    script, script_mut: SCRI => StringZ(StringZ),
+    npc, npc_mut: NPDT => Npc(Npc),
+    npc_flags, npc_flags_mut: FLAG => NpcFlags(FlagsAndBlood<NpcFlags>),
+    ai, ai_mut: AIDT => Ai(Ai),
+} [
+    items, items_mut: NPCO => Item(Item),
+    spells, spells_mut: NPCS => String(String),
+]);
+
+record_view!(CellRecord(CELL) until FRMR, MVRF {
+    id, id_mut: NAME => StringZ(StringZ),
+    cell, cell_mut: DATA => Cell(Cell),
+    region, region_mut: RGNN => StringZ(StringZ),
+    map_color, map_color_mut: NAM5 => I32(i32),
+    water, water_mut: WHGT => F32(f32),
+    interior, interior_mut: AMBI => Interior(Interior),
+    references_count, references_count_mut: NAM0 => I32(i32),
+} []);
+
+record_view!(ScriptRecord(SCPT) {
+    metadata, metadata_mut: SCHD => ScriptMetadata(ScriptMetadata),
+    vars, vars_mut: SCVR => StringZList(StringZList),
+    data, data_mut: SCDT => ScriptData(ScriptData),
+    text, text_mut: SCTX => StringList(Vec<String>),
+} []);
+
+record_view!(DialogueInfoRecord(INFO) {
+    id, id_mut: INAM => StringZ(StringZ),
+    prev_id, prev_id_mut: PNAM => StringZ(StringZ),
+    next_id, next_id_mut: NNAM => StringZ(StringZ),
+    info, info_mut: DATA => Info(Info),
+    actor, actor_mut: ONAM => StringZ(StringZ),
+    race, race_mut: RNAM => StringZ(StringZ),
+    class, class_mut: CNAM => StringZ(StringZ),
+    faction, faction_mut: FNAM => StringZ(StringZ),
+    cell, cell_mut: ANAM => StringZ(StringZ),
+    pc_faction, pc_faction_mut: DNAM => StringZ(StringZ),
+    sound, sound_mut: SNAM => StringZ(StringZ),
+    text, text_mut: NAME => String(String),
+    result, result_mut: BNAM => StringList(Vec<String>),
+} []);
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn npc_view_round_trip_keeps_unknown_fields() {
+        let record = Record {
+            tag: NPC_,
+            flags: RecordFlags::PERSIST,
+            fields: vec![
+                (NAME, Field::StringZ("fargoth".into())),
+                (Tag::from_str("XXXX").unwrap(), Field::U8List(vec![1, 2, 3])),
+                (FNAM, Field::StringZ("Fargoth".into())),
+                (NPCO, Field::Item(Item { count: 1, item_id: "gold_001".into() })),
+                (NPCO, Field::Item(Item { count: 2, item_id: "bread".into() })),
+            ]
+        };
+        let mut npc = NpcRecord::try_from(&record).unwrap();
+        assert_eq!(npc.id().unwrap().string, "fargoth");
+        assert_eq!(npc.name().unwrap().string, "Fargoth");
+        assert!(npc.script().is_none());
+        assert_eq!(npc.items().map(|x| x.count).sum::<i32>(), 3);
+        assert_eq!(Record::from(npc.clone()), record);
+        npc.name_mut().unwrap().string = "Fargoth the Liar".into();
+        let changed = Record::from(npc);
+        assert_eq!(changed.fields[1], record.fields[1]);
+        assert_eq!(changed.fields[2], (FNAM, Field::StringZ("Fargoth the Liar".into())));
+        let mut invalid = record.clone();
+        invalid.fields.push((AI_W, Field::I32(0)));
+        assert_eq!(
+            NpcRecord::try_from(&invalid),
+            Err(RecordViewError::UnexpectedField { record_tag: NPC_, field_tag: AI_W, field_index: 5 })
+        );
+    }
+
+    #[test]
+    fn cell_view_ignores_references() {
+        let record = Record {
+            tag: CELL,
+            flags: RecordFlags::empty(),
+            fields: vec![
+                (NAME, Field::StringZ("Seyda Neen".into())),
+                (DATA, Field::Cell(Cell { flags: CellFlags::empty(), position: CellPosition::Exterior { x: -2, y: -9 } })),
+                (FRMR, Field::I32(1)),
+                (NAME, Field::StringZ("chargen boat".into())),
+                (DATA, Field::PosRot(PosRot {
+                    pos: Pos { x: 0.0, y: 0.0, z: 0.0 },
+                    rot: Rot { x: 0.0, y: 0.0, z: 0.0 }
+                })),
+            ]
+        };
+        let cell = CellRecord::try_from(&record).unwrap();
+        assert_eq!(cell.id().unwrap().string, "Seyda Neen");
+        assert_eq!(cell.cell().unwrap().position, CellPosition::Exterior { x: -2, y: -9 });
+        assert_eq!(
+            NpcRecord::try_from(&record),
+            Err(RecordViewError::TagMismatch { expected: NPC_, actual: CELL })
+        );
+    }
+}
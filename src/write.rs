@@ -0,0 +1,363 @@
+use crate::code::{self, ser};
+use crate::code_page::CodePage;
+use crate::field::*;
+use crate::record::*;
+use either::{Right, Left, Either};
+use serde_serialize_seed::ValueWithSeed;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+#[cfg(feature="async")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+#[derive(Debug)]
+pub struct WriteRecordError {
+    source: Either<ser::Error, io::Error>,
+    record_tag: Tag,
+    record_offset: u64,
+}
+
+static INVALID_INPUT_IO_ERROR: LazyLock<io::Error> = LazyLock::new(|| io::Error::from(io::ErrorKind::InvalidInput));
+
+impl WriteRecordError {
+    pub fn record_tag(&self) -> Tag { self.record_tag }
+
+    pub fn record_offset(&self) -> u64 { self.record_offset }
+
+    pub fn as_io_error(&self) -> &io::Error {
+        self.source.as_ref().right_or_else(|_| &INVALID_INPUT_IO_ERROR)
+    }
+
+    pub fn into_io_error(self) -> io::Error {
+        self.source.right_or_else(|_| io::Error::from(io::ErrorKind::InvalidInput))
+    }
+
+    pub fn source(&self) -> Either<&ser::Error, &io::Error> { self.source.as_ref() }
+
+    pub fn into_source(self) -> Either<ser::Error, io::Error> { self.source }
+}
+
+impl From<WriteRecordError> for io::Error {
+    fn from(e: WriteRecordError) -> io::Error { e.into_io_error() }
+}
+
+impl Display for WriteRecordError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.source {
+            Left(ser_error) => write!(f, "{ser_error}"),
+            Right(io_error) => write!(f, "{io_error}"),
+        }?;
+        write!(f, " in {} record started at {:X}h", self.record_tag, self.record_offset)
+    }
+}
+
+impl Error for WriteRecordError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(match &self.source {
+            Left(ser_error) => ser_error,
+            Right(io_error) => io_error,
+        })
+    }
+}
+
+pub struct RecordWriter<'a, Output: Write + ?Sized> {
+    code_page: CodePage,
+    output: &'a mut Output,
+    omwsave: bool,
+    zip_mode: ZipMode,
+    offset: u64,
+    buf: Vec<u8>,
+}
+
+impl<'a, Output: Write + ?Sized> RecordWriter<'a, Output> {
+    pub fn new(code_page: CodePage, omwsave: bool, offset: u64, output: &'a mut Output) -> Self {
+        RecordWriter {
+            code_page,
+            output,
+            omwsave,
+            zip_mode: ZipMode::Compressed,
+            offset,
+            buf: Vec::new()
+        }
+    }
+
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.zip_mode = zip_mode;
+        self
+    }
+
+    pub fn offset(&self) -> u64 { self.offset }
+
+    pub fn write(&mut self, record: &Record) -> Result<(), WriteRecordError> {
+        serialize_record(self.code_page, self.omwsave, self.zip_mode, self.offset, record, &mut self.buf)?;
+        self.output.write_all(&self.buf).map_err(|io_error| WriteRecordError {
+            source: Right(io_error),
+            record_tag: record.tag,
+            record_offset: self.offset
+        })?;
+        self.offset += self.buf.len() as u64;
+        Ok(())
+    }
+}
+
+fn serialize_record(
+    code_page: CodePage,
+    omwsave: bool,
+    zip_mode: ZipMode,
+    offset: u64,
+    record: &Record,
+    buf: &mut Vec<u8>
+) -> Result<(), WriteRecordError> {
+    buf.clear();
+    code::serialize_into_vec(
+        &ValueWithSeed(record, RecordSerde { code_page: Some(code_page), omwsave, zip_mode }),
+        buf,
+        false
+    ).map_err(|ser_error| WriteRecordError {
+        source: Left(ser_error),
+        record_tag: record.tag,
+        record_offset: offset
+    })
+}
+
+#[cfg(feature="async")]
+pub struct AsyncRecordWriter<'a, Output: AsyncWrite + Unpin + ?Sized> {
+    code_page: CodePage,
+    output: &'a mut Output,
+    omwsave: bool,
+    zip_mode: ZipMode,
+    offset: u64,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature="async")]
+impl<'a, Output: AsyncWrite + Unpin + ?Sized> AsyncRecordWriter<'a, Output> {
+    pub fn new(code_page: CodePage, omwsave: bool, offset: u64, output: &'a mut Output) -> Self {
+        AsyncRecordWriter {
+            code_page,
+            output,
+            omwsave,
+            zip_mode: ZipMode::Compressed,
+            offset,
+            buf: Vec::new()
+        }
+    }
+
+    pub fn with_zip_mode(mut self, zip_mode: ZipMode) -> Self {
+        self.zip_mode = zip_mode;
+        self
+    }
+
+    pub fn offset(&self) -> u64 { self.offset }
+
+    pub async fn write(&mut self, record: &Record) -> Result<(), WriteRecordError> {
+        serialize_record(self.code_page, self.omwsave, self.zip_mode, self.offset, record, &mut self.buf)?;
+        self.output.write_all(&self.buf).await.map_err(|io_error| WriteRecordError {
+            source: Right(io_error),
+            record_tag: record.tag,
+            record_offset: self.offset
+        })?;
+        self.offset += self.buf.len() as u64;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum HeaderMode {
+    Keep,
+    UpdateRecords,
+    UpdateRecordsAndMasters(PathBuf),
+}
+
+fn master_metadata(masters_dir: &Path, master: &str) -> io::Result<fs::Metadata> {
+    let master_path = masters_dir.join(master);
+    let error = match fs::metadata(&master_path) {
+        Ok(metadata) => return Ok(metadata),
+        Err(e) => e,
+    };
+    if error.kind() == io::ErrorKind::NotFound {
+        let master = master.to_lowercase();
+        for entry in fs::read_dir(masters_dir)? {
+            let entry = entry?;
+            if entry.file_name().to_str().is_some_and(|x| x.to_lowercase() == master) {
+                return entry.metadata();
+            }
+        }
+    }
+    Err(io::Error::new(error.kind(), format!("{}: {error}", master_path.display())))
+}
+
+pub fn update_header(header: &mut Record, records: u32, mode: &HeaderMode) -> io::Result<()> {
+    if *mode == HeaderMode::Keep { return Ok(()); }
+    if header.tag != TES3 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} record found instead of {TES3}", header.tag)));
+    }
+    let file_metadata = header.fields.iter_mut().find_map(|(tag, field)| match (*tag, field) {
+        (HEDR, Field::FileMetadata(file_metadata)) => Some(file_metadata),
+        _ => None
+    }).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{TES3} record has no {HEDR} field")))?;
+    file_metadata.records = records;
+    let HeaderMode::UpdateRecordsAndMasters(masters_dir) = mode else { return Ok(()); };
+    let mut i = 0;
+    while i < header.fields.len() {
+        if let (MAST, Field::StringZ(master)) = &header.fields[i] {
+            let master_size = master_metadata(masters_dir, &master.string)?.len();
+            let master_size = Field::I64(master_size as i64);
+            if let Some((DATA, field)) = header.fields.get_mut(i + 1) {
+                *field = master_size;
+            } else {
+                header.fields.insert(i + 1, (DATA, master_size));
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+pub fn write_records<Output: Write + ?Sized>(
+    code_page: CodePage,
+    omwsave: bool,
+    header_mode: &HeaderMode,
+    records: &[Record],
+    output: &mut Output
+) -> Result<u64, WriteRecordError> {
+    let mut writer = RecordWriter::new(code_page, omwsave, 0, output);
+    let Some((header, records)) = records.split_first() else { return Ok(0); };
+    if *header_mode == HeaderMode::Keep {
+        writer.write(header)?;
+    } else {
+        let mut header = header.clone();
+        update_header(&mut header, records.len() as u32, header_mode).map_err(|io_error| WriteRecordError {
+            source: Right(io_error),
+            record_tag: header.tag,
+            record_offset: 0
+        })?;
+        writer.write(&header)?;
+    }
+    for record in records {
+        writer.write(record)?;
+    }
+    Ok(writer.offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::read::*;
+    use crate::write::*;
+    use either::Right;
+    use std::fs;
+    use std::io;
+    use std::str::FromStr;
+
+    fn test_records() -> Vec<Record> {
+        vec![
+            Record {
+                tag: TES3,
+                flags: RecordFlags::empty(),
+                fields: vec![
+                    (HEDR, Field::FileMetadata(FileMetadata {
+                        version: 7,
+                        file_type: FileType::ESP,
+                        author: Right("test author".into()),
+                        description: Right(vec!["test description".into()]),
+                        records: 1
+                    })),
+                    (MAST, Field::StringZ("Morrowind.esm".into())),
+                    (DATA, Field::I64(79837557))
+                ]
+            },
+            Record {
+                tag: Tag::from_str("CLOH").unwrap(),
+                flags: RecordFlags::PERSIST,
+                fields: vec![
+                    (Tag::from_str("NAMF").unwrap(), Field::U8List(b"namename".to_vec())),
+                ]
+            }
+        ]
+    }
+
+    #[test]
+    fn write_then_read_records() {
+        let records = test_records();
+        let mut bytes = Vec::new();
+        let written = write_records(CodePage::English, false, &HeaderMode::Keep, &records, &mut bytes).unwrap();
+        assert_eq!(written, bytes.len() as u64);
+        let mut input = &bytes[..];
+        let read = Records::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut input)
+            .map(|x| x.unwrap()).collect::<Vec<_>>();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn write_error_has_record_offset() {
+        let records = test_records();
+        let mut bytes = [0u8; 370];
+        let mut output = &mut bytes[..];
+        let mut writer = RecordWriter::new(CodePage::English, false, 0, &mut output);
+        writer.write(&records[0]).unwrap();
+        let tes3_size = writer.offset();
+        let error = writer.write(&records[1]).unwrap_err();
+        assert_eq!(error.record_tag(), Tag::from_str("CLOH").unwrap());
+        assert_eq!(error.record_offset(), tes3_size);
+        assert_eq!(error.as_io_error().kind(), io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn write_records_updates_header() {
+        let mut records = test_records();
+        records.push(records[1].clone());
+        if let Field::FileMetadata(file_metadata) = &mut records[0].fields[0].1 {
+            file_metadata.records = 0;
+        }
+        records[0].fields.pop();
+        let masters_dir = std::env::temp_dir().join(format!("esl_write_records_updates_header_{}", std::process::id()));
+        fs::create_dir_all(&masters_dir).unwrap();
+        fs::write(masters_dir.join("morrowind.ESM"), [0u8; 17]).unwrap();
+        let mut bytes = Vec::new();
+        write_records(
+            CodePage::English, false, &HeaderMode::UpdateRecordsAndMasters(masters_dir.clone()), &records, &mut bytes
+        ).unwrap();
+        fs::remove_dir_all(&masters_dir).unwrap();
+        let mut input = &bytes[..];
+        let read = Records::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut input)
+            .map(|x| x.unwrap()).collect::<Vec<_>>();
+        let Field::FileMetadata(file_metadata) = &read[0].fields[0].1 else { panic!() };
+        assert_eq!(file_metadata.records, 2);
+        assert_eq!(&read[0].fields[1..], &[(MAST, Field::StringZ("Morrowind.esm".into())), (DATA, Field::I64(17))]);
+    }
+
+    #[cfg(feature="async")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[cfg(feature="async")]
+    #[test]
+    fn async_write_then_read_records() {
+        let records = test_records();
+        let mut bytes = Vec::new();
+        let mut writer = AsyncRecordWriter::new(CodePage::English, false, 0, &mut bytes);
+        for record in &records {
+            block_on(writer.write(record)).unwrap();
+        }
+        assert_eq!(writer.offset(), bytes.len() as u64);
+        let mut input = &bytes[..];
+        let mut reader = AsyncRecords::new(CodePage::English, RecordReadMode::Strict, false, 0, &mut input);
+        let mut read = Vec::new();
+        while let Some(record) = block_on(reader.next_record()) {
+            read.push(record.unwrap());
+        }
+        assert_eq!(read, records);
+    }
+}